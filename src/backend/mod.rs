@@ -1,26 +1,91 @@
 pub mod z2m;
 
-use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
-use tokio::sync::broadcast::Receiver;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::oneshot;
+use tokio::time::{timeout_at, Instant};
 
 use crate::error::ApiResult;
-use crate::hue::api::{GroupedLightUpdate, LightUpdate, ResourceLink, Scene, SceneUpdate};
+use crate::hue::api::{
+    DeviceUpdate, GroupedLightUpdate, LightUpdate, ResourceLink, Room, RoomUpdate, Scene,
+    SceneUpdate, ZigbeeDeviceDiscoveryUpdate, Zone, ZoneUpdate,
+};
 
 #[derive(Clone, Debug)]
 pub enum BackendRequest {
     LightUpdate(ResourceLink, LightUpdate),
 
+    /// Read back the current state of a light from the device
+    LightRefresh(ResourceLink),
+
     SceneCreate(ResourceLink, u32, Scene),
     SceneUpdate(ResourceLink, SceneUpdate),
 
     GroupedLightUpdate(ResourceLink, GroupedLightUpdate),
 
+    RoomCreate(ResourceLink, Room),
+    RoomUpdate(ResourceLink, RoomUpdate),
+
+    ZoneCreate(ResourceLink, Zone),
+    ZoneUpdate(ResourceLink, ZoneUpdate),
+
+    ZigbeeDeviceDiscoveryUpdate(ResourceLink, ZigbeeDeviceDiscoveryUpdate),
+
+    DeviceUpdate(ResourceLink, DeviceUpdate),
+
+    /// Remove a device from the network. If the flag is set, the device is
+    /// removed even if it does not respond.
+    DeviceDelete(ResourceLink, bool),
+
     Delete(ResourceLink),
 }
 
+/// Channel for reporting the outcome of a [`BackendRequest`]
+pub type BackendReply = oneshot::Sender<ApiResult<()>>;
+
+/// A [`BackendRequest`], as queued for the backend that owns it
+#[derive(Debug)]
+pub struct QueuedRequest {
+    pub req: BackendRequest,
+    pub reply: BackendReply,
+}
+
+/// Pending outcome of a queued [`BackendRequest`]
+///
+/// Requests for all backends (e.g. device discovery) are tracked for each
+/// backend separately.
+#[derive(Debug)]
+pub struct BackendAck(Vec<oneshot::Receiver<ApiResult<()>>>);
+
+impl BackendAck {
+    /// How long to wait for a backend to report a failure
+    pub const TIMEOUT: Duration = Duration::from_secs(2);
+
+    #[must_use]
+    pub const fn new(rxs: Vec<oneshot::Receiver<ApiResult<()>>>) -> Self {
+        Self(rxs)
+    }
+
+    /// Wait for the backends to report the outcome of the request.
+    ///
+    /// Not every command is explicitly confirmed (z2m only reports failed
+    /// device updates), so a request is considered successful if no failure
+    /// is reported within [`Self::TIMEOUT`], or if the backend drops it
+    /// without a verdict.
+    pub async fn wait(self) -> ApiResult<()> {
+        let deadline = Instant::now() + Self::TIMEOUT;
+        for rx in self.0 {
+            if let Ok(Ok(Err(err))) = timeout_at(deadline, rx).await {
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+}
+
 #[async_trait]
 pub trait Backend {
-    async fn run_forever(self, chan: Receiver<Arc<BackendRequest>>) -> ApiResult<()>;
+    async fn run_forever(self, chan: UnboundedReceiver<QueuedRequest>) -> ApiResult<()>;
 }
@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+/// Delay between reconnection attempts to z2m.
+///
+/// The delay doubles after each failed attempt, up to [`Self::MAX`]. Half of
+/// each delay is random, so several bifrost instances (or several servers in
+/// one instance) do not all hammer z2m at the same moment.
+#[derive(Debug, Default)]
+pub struct Backoff {
+    attempt: u32,
+}
+
+impl Backoff {
+    pub const MIN: Duration = Duration::from_secs(1);
+    pub const MAX: Duration = Duration::from_secs(60);
+
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { attempt: 0 }
+    }
+
+    /// Forget earlier failures, after a successful connection
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+
+    /// Time to wait before the next attempt
+    pub fn next_delay(&mut self) -> Duration {
+        let base = Self::MIN
+            .saturating_mul(1 << self.attempt.min(16))
+            .min(Self::MAX);
+        self.attempt = self.attempt.saturating_add(1);
+
+        let half = base / 2;
+        half + rand::rng().random_range(Duration::ZERO..=half)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::z2m::backoff::Backoff;
+
+    #[test]
+    fn backoff_grows_and_resets() {
+        let mut backoff = Backoff::new();
+
+        let first = backoff.next_delay();
+        assert!(first >= Backoff::MIN / 2 && first <= Backoff::MIN);
+
+        for _ in 0..20 {
+            assert!(backoff.next_delay() <= Backoff::MAX);
+        }
+        assert!(backoff.next_delay() >= Backoff::MAX / 2);
+
+        backoff.reset();
+        assert!(backoff.next_delay() <= Backoff::MIN);
+    }
+}
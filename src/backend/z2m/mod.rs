@@ -1,4 +1,9 @@
+pub mod backoff;
+pub mod mqtt;
+pub mod queue;
+
 use std::collections::{HashMap, HashSet};
+use std::iter;
 use std::sync::Arc;
 
 use async_trait::async_trait;
@@ -8,24 +13,27 @@ use serde::Deserialize;
 use serde_json::{json, Value};
 use tokio::net::TcpStream;
 use tokio::select;
-use tokio::sync::broadcast::Receiver;
-use tokio::sync::Mutex;
-use tokio::time::sleep;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::{oneshot, Mutex};
+use tokio::time::{sleep_until, Instant};
 use tokio_tungstenite::{connect_async, tungstenite, MaybeTlsStream, WebSocketStream};
 use uuid::Uuid;
 
-use crate::backend::{Backend, BackendRequest};
-use crate::config::{AppConfig, Z2mServer};
+use crate::backend::z2m::backoff::Backoff;
+use crate::backend::z2m::queue::RequestQueue;
+use crate::backend::{Backend, BackendAck, BackendReply, BackendRequest, QueuedRequest};
+use crate::config::{AppConfig, OfflinePolicy, Z2mServer};
 use crate::error::{ApiError, ApiResult};
 use crate::hue;
 use crate::hue::api::{
-    Button, ButtonData, ButtonMetadata, ButtonReport, ColorTemperature, ColorTemperatureUpdate,
-    ColorUpdate, Device, DeviceArchetype, DeviceProductData, Dimming, DimmingUpdate, GroupedLight,
-    Light, LightColor, LightEffect, LightEffectStatus, LightEffectValues, LightEffects,
-    LightEffectsV2, LightEffectsV2Update, LightGradient, LightGradientMode, LightMetadata,
-    LightUpdate, Metadata, RType, Resource, ResourceLink, Room, RoomArchetype, RoomMetadata, Scene,
-    SceneAction, SceneActionElement, SceneActive, SceneMetadata, SceneRecall, SceneStatus,
-    SceneStatusUpdate, ZigbeeConnectivity, ZigbeeConnectivityStatus,
+    Button, ColorTemperature, ColorTemperatureUpdate, ColorUpdate, Device, DeviceArchetype,
+    DevicePower, DeviceProductData, Dimming, GroupedLight, Light, LightColor, LightEffect,
+    LightEffectStatus, LightEffectValues, LightEffects, LightEffectsV2, LightEffectsV2Update,
+    LightGradient, LightGradientMode, LightLevel, LightMetadata, LightUpdate, Metadata, Motion,
+    PowerState, RType, RelativeRotary, Resource, ResourceLink, Room, RoomArchetype, RoomMetadata,
+    Rotation, Scene, SceneAction, SceneActionElement, SceneActive, SceneMetadata, SceneRecall,
+    SceneStatus, SceneStatusUpdate, Temperature, ZigbeeConnectivity, ZigbeeConnectivityStatus,
+    ZigbeeDeviceDiscovery, ZigbeeDeviceDiscoveryStatus, Zone,
 };
 use crate::hue::scene_icons;
 use crate::hue::zigbee::{EffectType, GradientParams, GradientStyle, HueZigbeeUpdate};
@@ -33,9 +41,13 @@ use crate::model::clamp::Clamp;
 use crate::model::hexcolor::HexColor;
 use crate::model::state::AuxData;
 use crate::resource::Resources;
-use crate::z2m::api::{self, ExposeLight, Message, RawMessage};
+use crate::z2m::action::{self, SwitchLayout};
+use crate::z2m::api::{
+    self, Availability, AvailabilityPayload, BridgeEvent, BridgeInfo, BridgeResponse, ExposeLight,
+    InterviewStatus, Message, RawMessage,
+};
 use crate::z2m::request::Z2mRequest;
-use crate::z2m::update::{DeviceColor, DeviceUpdate};
+use crate::z2m::update::{DeviceColor, DeviceState, DeviceUpdate};
 
 #[derive(Debug)]
 struct LearnScene {
@@ -44,6 +56,47 @@ struct LearnScene {
     pub known: HashMap<Uuid, SceneAction>,
 }
 
+/// How z2m reports the outcome of a request sent to it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AckKey {
+    /// Device update: z2m does not tag `/set` requests, or confirm them. A
+    /// failure is only reported as a `bridge/logging` message for the device
+    /// topic. State reports on the topic are not used, since they can also
+    /// be periodic reports, or answers to `/get` requests.
+    Topic(String),
+    /// Bridge request: z2m replies on `bridge/response/*` with the transaction id
+    Transaction(String),
+}
+
+#[derive(Debug)]
+struct PendingAck {
+    pub expire: DateTime<Utc>,
+    pub reply: BackendReply,
+}
+
+/// A request sent as several bridge requests. It is acked once all of them
+/// have replied, and fails if any of them failed.
+#[derive(Debug)]
+struct AckBatch {
+    pub expire: DateTime<Utc>,
+    pub waiting: HashSet<AckKey>,
+    pub res: Result<(), String>,
+}
+
+/// Transport used for sending messages to z2m
+#[async_trait]
+pub trait Z2mSink: Send {
+    async fn send_message(&mut self, msg: RawMessage) -> ApiResult<()>;
+}
+
+#[async_trait]
+impl Z2mSink for WebSocketStream<MaybeTlsStream<TcpStream>> {
+    async fn send_message(&mut self, msg: RawMessage) -> ApiResult<()> {
+        let json = serde_json::to_string(&msg)?;
+        Ok(self.send(tungstenite::Message::text(json)).await?)
+    }
+}
+
 pub struct Z2mBackend {
     name: String,
     server: Z2mServer,
@@ -52,10 +105,30 @@ pub struct Z2mBackend {
     map: HashMap<String, Uuid>,
     rmap: HashMap<Uuid, String>,
     learn: HashMap<Uuid, LearnScene>,
+    /// Scenes being stored again after an edit. They briefly disappear from
+    /// z2m's group list, and must not be deleted meanwhile.
+    editing: HashSet<Uuid>,
     ignore: HashSet<String>,
+    switches: HashMap<Uuid, SwitchLayout>,
+    pending: HashMap<AckKey, Vec<PendingAck>>,
+    batches: HashMap<AckKey, AckBatch>,
+    queue: RequestQueue,
+    permit_join: Option<bool>,
+    removing: HashMap<String, ResourceLink>,
+    creating: HashMap<AckKey, ResourceLink>,
+    online: bool,
+    offline_zbc: HashSet<Uuid>,
+    seen_devices: bool,
+    seen_groups: bool,
+    /// Lights whose state was read back since connecting. Changes may have
+    /// been missed while disconnected, so this is cleared on reconnect.
+    refreshed: HashSet<Uuid>,
 }
 
 impl Z2mBackend {
+    /// Length of the join window opened by device discovery, in seconds
+    const PERMIT_JOIN_TIME: u32 = 60;
+
     pub fn new(
         name: String,
         server: Z2mServer,
@@ -66,6 +139,9 @@ impl Z2mBackend {
         let rmap = HashMap::new();
         let learn = HashMap::new();
         let ignore = HashSet::new();
+        let switches = HashMap::new();
+        let pending = HashMap::new();
+        let queue = RequestQueue::new(server.get_request_interval());
         Ok(Self {
             name,
             server,
@@ -74,7 +150,20 @@ impl Z2mBackend {
             map,
             rmap,
             learn,
+            editing: HashSet::new(),
             ignore,
+            switches,
+            pending,
+            batches: HashMap::new(),
+            queue,
+            permit_join: None,
+            removing: HashMap::new(),
+            creating: HashMap::new(),
+            online: false,
+            offline_zbc: HashSet::new(),
+            seen_devices: false,
+            seen_groups: false,
+            refreshed: HashSet::new(),
         })
     }
 
@@ -83,6 +172,9 @@ impl Z2mBackend {
 
         let link_device = RType::Device.deterministic(&dev.ieee_address);
         let link_light = RType::Light.deterministic(&dev.ieee_address);
+        let link_zbc = RType::ZigbeeConnectivity.deterministic(&dev.ieee_address);
+
+        let zbc = ZigbeeConnectivity::new(link_device, dev.ieee_address.to_mac_address());
 
         let product_data = DeviceProductData::guess_from_device(dev);
         let metadata = LightMetadata::new(DeviceArchetype::SpotBulb, name);
@@ -94,7 +186,7 @@ impl Z2mBackend {
         let dev = hue::api::Device {
             product_data,
             metadata: metadata.clone().into(),
-            services: vec![link_light],
+            services: vec![link_light, link_zbc],
             identify: None,
             usertest: None,
         };
@@ -103,6 +195,8 @@ impl Z2mBackend {
         self.rmap.insert(link_light.rid, name.to_string());
 
         let mut res = self.state.lock().await;
+        res.set_owner(&link_device, &self.name);
+        res.set_owner(&link_light, &self.name);
         let mut light = Light::new(link_device, metadata);
 
         light.dimming = expose
@@ -144,58 +238,161 @@ impl Z2mBackend {
 
         res.aux_set(&link_light, AuxData::new().with_topic(name));
         res.add(&link_device, Resource::Device(dev))?;
+        res.ensure_service(&link_device, link_zbc)?;
         res.add(&link_light, Resource::Light(light))?;
+        res.add(&link_zbc, Resource::ZigbeeConnectivity(zbc))?;
         drop(res);
 
+        /* the light keeps its default state until the device reports, so ask for it */
+        if self.refreshed.insert(link_light.rid) {
+            self.queue_light_refresh(link_light);
+        }
+
         Ok(())
     }
 
-    pub async fn add_switch(&mut self, dev: &api::Device) -> ApiResult<()> {
+    /// Queue a read of the light state, paced like any other request
+    fn queue_light_refresh(&mut self, link: ResourceLink) {
+        let (reply, _) = oneshot::channel();
+        self.queue.push(QueuedRequest {
+            req: BackendRequest::LightRefresh(link),
+            reply,
+        });
+    }
+
+    pub async fn add_sensor(&mut self, dev: &api::Device) -> ApiResult<()> {
         let name = &dev.friendly_name;
 
         let link_device = RType::Device.deterministic(&dev.ieee_address);
-        let link_button = RType::Button.deterministic(&dev.ieee_address);
+        let link_motion = RType::Motion.deterministic(&dev.ieee_address);
+        let link_light_level = RType::LightLevel.deterministic(&dev.ieee_address);
+        let link_temperature = RType::Temperature.deterministic(&dev.ieee_address);
+        let link_power = RType::DevicePower.deterministic(&dev.ieee_address);
         let link_zbc = RType::ZigbeeConnectivity.deterministic(&dev.ieee_address);
 
+        let zbc = ZigbeeConnectivity::new(link_device, dev.ieee_address.to_mac_address());
+
+        let mut services = vec![];
+
+        let motion = dev.expose_occupancy().then(|| {
+            services.push(link_motion);
+            Motion::new(link_device)
+        });
+
+        let light_level = dev.expose_illuminance().then(|| {
+            services.push(link_light_level);
+            LightLevel::new(link_device)
+        });
+
+        let temperature = dev.expose_temperature().then(|| {
+            services.push(link_temperature);
+            Temperature::new(link_device)
+        });
+
+        let power = dev.is_battery_powered().then(|| {
+            services.push(link_power);
+            DevicePower::new(link_device)
+        });
+
+        services.push(link_zbc);
+
         let dev = hue::api::Device {
             product_data: DeviceProductData::guess_from_device(dev),
-            metadata: Metadata::new(DeviceArchetype::UnknownArchetype, "foo"),
-            services: vec![link_button, link_zbc],
+            metadata: Metadata::new(DeviceArchetype::UnknownArchetype, name),
+            services,
             identify: None,
             usertest: None,
         };
 
-        self.map.insert(name.to_string(), link_button.rid);
-        self.rmap.insert(link_button.rid, name.to_string());
+        self.map.insert(name.clone(), link_device.rid);
+        self.rmap.insert(link_device.rid, name.clone());
 
         let mut res = self.state.lock().await;
-        let button = Button {
-            owner: link_device,
-            metadata: ButtonMetadata { control_id: 0 },
-            button: ButtonData {
-                last_event: None,
-                button_report: Some(ButtonReport {
-                    updated: Utc::now(),
-                    event: String::from("initial_press"),
-                }),
-                repeat_interval: Some(100),
-                event_values: Some(json!(["initial_press", "repeat"])),
-            },
-        };
+        res.set_owner(&link_device, &self.name);
+        res.aux_set(&link_device, AuxData::new().with_topic(name));
+        res.add(&link_device, Resource::Device(dev))?;
+        res.ensure_service(&link_device, link_zbc)?;
+
+        if let Some(motion) = motion {
+            res.add(&link_motion, Resource::Motion(motion))?;
+        }
+
+        if let Some(light_level) = light_level {
+            res.add(&link_light_level, Resource::LightLevel(light_level))?;
+        }
+
+        if let Some(temperature) = temperature {
+            res.add(&link_temperature, Resource::Temperature(temperature))?;
+        }
+
+        if let Some(power) = power {
+            res.add(&link_power, Resource::DevicePower(power))?;
+        }
+
+        res.add(&link_zbc, Resource::ZigbeeConnectivity(zbc))?;
+        drop(res);
+
+        Ok(())
+    }
+
+    pub async fn add_switch(&mut self, dev: &api::Device) -> ApiResult<()> {
+        let name = &dev.friendly_name;
+        let layout = SwitchLayout::from_device(dev);
+
+        let link_device = RType::Device.deterministic(&dev.ieee_address);
+        let link_rotary = RType::RelativeRotary.deterministic(&dev.ieee_address);
+        let link_power = RType::DevicePower.deterministic(&dev.ieee_address);
+        let link_zbc = RType::ZigbeeConnectivity.deterministic(&dev.ieee_address);
+        let link_buttons: Vec<ResourceLink> = (0..layout.buttons.len())
+            .map(|idx| RType::Button.deterministic((&dev.ieee_address, idx)))
+            .collect();
+
+        let mut services = link_buttons.clone();
+        if layout.rotary.is_some() {
+            services.push(link_rotary);
+        }
+        let has_power = dev.is_battery_powered();
+        if has_power {
+            services.push(link_power);
+        }
+        services.push(link_zbc);
+
+        let zbc = ZigbeeConnectivity::new(link_device, dev.ieee_address.to_mac_address());
 
-        let zbc = ZigbeeConnectivity {
-            owner: link_device,
-            mac_address: String::from("11:22:33:44:55:66:77:89"),
-            status: ZigbeeConnectivityStatus::ConnectivityIssue,
-            channel: Some(json!({
-                "status": "set",
-                "value": "channel_25",
-            })),
-            extended_pan_id: None,
+        let dev = hue::api::Device {
+            product_data: DeviceProductData::guess_from_device(dev),
+            metadata: Metadata::new(DeviceArchetype::UnknownArchetype, name),
+            services,
+            identify: None,
+            usertest: None,
         };
 
+        self.map.insert(name.clone(), link_device.rid);
+        self.rmap.insert(link_device.rid, name.clone());
+        let has_rotary = layout.rotary.is_some();
+        self.switches.insert(link_device.rid, layout);
+
+        let mut res = self.state.lock().await;
+        res.set_owner(&link_device, &self.name);
+        res.aux_set(&link_device, AuxData::new().with_topic(name));
         res.add(&link_device, Resource::Device(dev))?;
-        res.add(&link_button, Resource::Button(button))?;
+        res.ensure_service(&link_device, link_zbc)?;
+
+        for (control_id, link_button) in (1..).zip(&link_buttons) {
+            let button = Button::new(link_device, control_id);
+            res.add(link_button, Resource::Button(button))?;
+        }
+
+        if has_rotary {
+            let rotary = RelativeRotary::new(link_device);
+            res.add(&link_rotary, Resource::RelativeRotary(rotary))?;
+        }
+
+        if has_power {
+            let power = DevicePower::new(link_device);
+            res.add(&link_power, Resource::DevicePower(power))?;
+        }
+
         res.add(&link_zbc, Resource::ZigbeeConnectivity(zbc))?;
         drop(res);
 
@@ -203,6 +400,121 @@ impl Z2mBackend {
     }
 
     #[allow(clippy::too_many_lines)]
+    fn group_scene(link_room: ResourceLink, scn: &api::Scene) -> Scene {
+        Scene {
+            actions: vec![],
+            auto_dynamic: false,
+            group: link_room,
+            metadata: SceneMetadata {
+                appdata: None,
+                image: guess_scene_icon(&scn.name),
+                name: scn.name.to_string(),
+            },
+            palette: json!({
+                "color": [],
+                "dimming": [],
+                "color_temperature": [],
+                "effects": [],
+            }),
+            speed: 0.5,
+            recall: SceneRecall {
+                action: None,
+                dimming: None,
+                duration: None,
+            },
+            status: Some(SceneStatus {
+                active: SceneActive::Inactive,
+                last_recall: None,
+            }),
+        }
+    }
+
+    /// Find the room or zone for a z2m group.
+    ///
+    /// Rooms and zones created from the hue app are looked up by topic, since
+    /// their id is assigned before the group exists. Any other group is a room.
+    fn find_group(res: &Resources, topic: &str) -> ResourceLink {
+        [RType::Zone, RType::Room]
+            .into_iter()
+            .flat_map(|rtype| {
+                res.get_resources_by_type(rtype)
+                    .into_iter()
+                    .map(move |rr| rtype.link_to(rr.id))
+            })
+            .find(|link| {
+                res.aux_get(link)
+                    .is_ok_and(|aux| aux.topic.as_deref() == Some(topic))
+            })
+            .unwrap_or_else(|| RType::Room.deterministic(topic))
+    }
+
+    /// The services of a room or zone, or `None` for any other resource
+    fn group_services(res: &Resources, uuid: &Uuid) -> Option<Vec<ResourceLink>> {
+        match res.get_resource_by_id(uuid).ok()?.obj {
+            Resource::Room(room) => Some(room.services),
+            Resource::Zone(zone) => Some(zone.services),
+            _ => None,
+        }
+    }
+
+    /// Delete the rooms and zones of z2m groups that no longer exist
+    async fn remove_stale_groups(&mut self, groups: &[api::Group]) -> ApiResult<()> {
+        let topics: HashSet<&str> = groups
+            .iter()
+            .map(|grp| grp.friendly_name.as_str())
+            .collect();
+
+        let mut res = self.state.lock().await;
+
+        let stale: Vec<(Uuid, String, Vec<ResourceLink>)> = self
+            .rmap
+            .iter()
+            .filter(|(_, topic)| !topics.contains(topic.as_str()))
+            .filter_map(|(uuid, topic)| {
+                Self::group_services(&res, uuid).map(|svcs| (*uuid, topic.clone(), svcs))
+            })
+            .collect();
+
+        for (uuid, topic, services) in stale {
+            let link_group = res.get_resource_by_id(&uuid)?.obj.rtype().link_to(uuid);
+            log::info!(
+                "[{}] Group {topic} was removed, deleting {link_group:?}",
+                self.name
+            );
+
+            for scene in res.get_scenes_for_room(&uuid) {
+                res.delete(&RType::Scene.link_to(scene))?;
+            }
+
+            for svc in &services {
+                self.rmap.remove(&svc.rid);
+                if res.get_resource_by_id(&svc.rid).is_ok() {
+                    res.delete(svc)?;
+                }
+            }
+
+            self.map.remove(&topic);
+            self.rmap.remove(&uuid);
+            res.delete(&link_group)?;
+        }
+        drop(res);
+
+        Ok(())
+    }
+
+    /// Apply the name and icon from the `rooms` config section, if any
+    fn apply_room_config(&self, topic: &str, metadata: &mut RoomMetadata) {
+        let Some(room_conf) = self.config.rooms.get(topic) else {
+            return;
+        };
+        if let Some(name) = &room_conf.name {
+            metadata.name = name.to_string();
+        }
+        if let Some(icon) = &room_conf.icon {
+            metadata.archetype = *icon;
+        }
+    }
+
     pub async fn add_group(&mut self, grp: &crate::z2m::api::Group) -> ApiResult<()> {
         let room_name;
 
@@ -221,75 +533,53 @@ impl Z2mBackend {
             room_name = &grp.friendly_name;
         }
 
-        let link_room = RType::Room.deterministic(&grp.friendly_name);
-        let link_glight = RType::GroupedLight.deterministic((link_room.rid, grp.id));
-
-        let children = grp
-            .members
-            .iter()
-            .map(|f| RType::Device.deterministic(&f.ieee_address))
-            .collect();
-
         let topic = grp.friendly_name.to_string();
 
         let mut res = self.state.lock().await;
 
-        let mut scenes_new = HashSet::new();
-
-        for scn in &grp.scenes {
-            let scene = Scene {
-                actions: vec![],
-                auto_dynamic: false,
-                group: link_room,
-                metadata: SceneMetadata {
-                    appdata: None,
-                    image: guess_scene_icon(&scn.name),
-                    name: scn.name.to_string(),
-                },
-                palette: json!({
-                    "color": [],
-                    "dimming": [],
-                    "color_temperature": [],
-                    "effects": [],
-                }),
-                speed: 0.5,
-                recall: SceneRecall {
-                    action: None,
-                    dimming: None,
-                    duration: None,
-                },
-                status: Some(SceneStatus {
-                    active: SceneActive::Inactive,
-                    last_recall: None,
-                }),
-            };
+        let link_room = Self::find_group(&res, &topic);
+        let link_glight = RType::GroupedLight.deterministic((link_room.rid, grp.id));
 
-            let link_scene = RType::Scene.deterministic((link_room.rid, scn.id));
+        /* the group id is needed to store scenes on the member lights */
+        res.aux_set(
+            &link_room,
+            AuxData::new().with_topic(&topic).with_index(grp.id),
+        );
 
-            res.aux_set(
-                &link_scene,
-                AuxData::new().with_topic(&topic).with_index(scn.id),
-            );
+        /* rooms contain devices, zones contain the lights of those devices */
+        let rtype = if link_room.rtype == RType::Zone {
+            RType::Light
+        } else {
+            RType::Device
+        };
 
-            scenes_new.insert(link_scene.rid);
-            res.add(&link_scene, Resource::Scene(scene))?;
-        }
+        /* members may not be imported yet, until the device list has been seen */
+        let children: Vec<_> = grp
+            .members
+            .iter()
+            .map(|f| rtype.deterministic(&f.ieee_address))
+            .filter(|link| !self.seen_devices || res.get_resource_by_id(&link.rid).is_ok())
+            .collect();
 
-        if let Ok(room) = res.get::<Room>(&link_room) {
-            log::info!(
-                "[{}] {link_room:?} ({}) known, updating..",
-                self.name,
-                room.metadata.name
-            );
+        let scenes_new: HashSet<Uuid> = grp
+            .scenes
+            .iter()
+            .map(|scn| RType::Scene.deterministic((link_room.rid, scn.id)).rid)
+            .collect();
+
+        if res.get_resource_by_id(&link_room.rid).is_ok() {
+            log::info!("[{}] {link_room:?} ({topic}) known, updating..", self.name);
 
             let scenes_old: HashSet<Uuid> =
                 HashSet::from_iter(res.get_scenes_for_room(&link_room.rid));
 
             log::trace!("[{}] old scenes: {scenes_old:?}", self.name);
             log::trace!("[{}] new scenes: {scenes_new:?}", self.name);
+            self.editing.retain(|uuid| !scenes_new.contains(uuid));
+
             let gone = scenes_old.difference(&scenes_new);
             log::trace!("[{}]   deleted: {gone:?}", self.name);
-            for uuid in gone {
+            for uuid in gone.filter(|uuid| !self.editing.contains(uuid)) {
                 log::debug!(
                     "[{}] Deleting orphaned {uuid:?} in {link_room:?}",
                     self.name
@@ -304,36 +594,70 @@ impl Z2mBackend {
             );
         }
 
-        let mut metadata = RoomMetadata::new(RoomArchetype::Home, room_name);
-        if let Some(room_conf) = self.config.rooms.get(&topic) {
-            if let Some(name) = &room_conf.name {
-                metadata.name = name.to_string();
-            }
-            if let Some(icon) = &room_conf.icon {
-                metadata.archetype = *icon;
-            }
-        };
-
-        let room = Room {
-            children,
-            metadata,
-            services: vec![link_glight],
-        };
-
         self.map.insert(topic.clone(), link_glight.rid);
         self.rmap.insert(link_glight.rid, topic.clone());
         self.rmap.insert(link_room.rid, topic.clone());
 
-        res.add(&link_room, Resource::Room(room))?;
+        res.set_owner(&link_room, &self.name);
+        res.set_owner(&link_glight, &self.name);
+
+        /* known rooms keep their metadata, since it can be changed from the hue app */
+        if link_room.rtype == RType::Zone {
+            res.update::<Zone>(&link_room.rid, |zone| {
+                zone.children = children;
+                zone.services = vec![link_glight];
+                self.apply_room_config(&topic, &mut zone.metadata);
+            })?;
+        } else if res.get::<Room>(&link_room).is_ok() {
+            res.update::<Room>(&link_room.rid, |room| {
+                room.children = children;
+                room.services = vec![link_glight];
+                self.apply_room_config(&topic, &mut room.metadata);
+            })?;
+        } else {
+            let mut metadata = RoomMetadata::new(RoomArchetype::Home, room_name);
+            self.apply_room_config(&topic, &mut metadata);
+
+            let room = Room {
+                children,
+                metadata,
+                services: vec![link_glight],
+            };
+            res.add(&link_room, Resource::Room(room))?;
+        }
 
         let glight = GroupedLight::new(link_room);
 
         res.add(&link_glight, Resource::GroupedLight(glight))?;
+
+        /* scenes can only be added once their room exists */
+        Self::add_group_scenes(&mut res, link_room, &topic, &grp.scenes)?;
         drop(res);
 
         Ok(())
     }
 
+    fn add_group_scenes(
+        res: &mut Resources,
+        link_room: ResourceLink,
+        topic: &str,
+        scenes: &[api::Scene],
+    ) -> ApiResult<()> {
+        for scn in scenes {
+            let scene = Self::group_scene(link_room, scn);
+            let link_scene = RType::Scene.deterministic((link_room.rid, scn.id));
+
+            res.aux_set(
+                &link_scene,
+                AuxData::new().with_topic(topic).with_index(scn.id),
+            );
+
+            res.add(&link_scene, Resource::Scene(scene))?;
+        }
+
+        Ok(())
+    }
+
     pub async fn handle_update(&mut self, rid: &Uuid, payload: &Value) -> ApiResult<()> {
         let upd = DeviceUpdate::deserialize(payload)?;
 
@@ -344,11 +668,12 @@ impl Z2mBackend {
                     log::error!("FAIL: {e:?} in {upd:?}");
                 }
             }
-            Resource::GroupedLight(_) => {
-                if let Err(e) = self.handle_update_grouped_light(rid, &upd).await {
+            Resource::Device(dev) => {
+                if let Err(e) = self.handle_update_device(rid, &dev, &upd).await {
                     log::error!("FAIL: {e:?} in {upd:?}");
                 }
             }
+            /* grouped light state is aggregated from the member lights */
             _ => {}
         }
 
@@ -372,6 +697,7 @@ impl Z2mBackend {
 
             *light += upd;
         })?;
+        res.reconcile_pending(uuid);
 
         for learn in self.learn.values_mut() {
             if learn.missing.remove(uuid) {
@@ -422,28 +748,134 @@ impl Z2mBackend {
         Ok(())
     }
 
-    async fn handle_update_grouped_light(&self, uuid: &Uuid, upd: &DeviceUpdate) -> ApiResult<()> {
+    async fn handle_update_device(
+        &self,
+        uuid: &Uuid,
+        dev: &Device,
+        upd: &DeviceUpdate,
+    ) -> ApiResult<()> {
         let mut res = self.state.lock().await;
-        res.update::<GroupedLight>(uuid, |glight| {
-            if let Some(state) = &upd.state {
-                glight.on = Some((*state).into());
+
+        /* button and rotary events are reported every time, even if repeated */
+        if let (Some(act), Some(layout)) = (&upd.action, self.switches.get(uuid)) {
+            self.handle_switch_action(&mut res, dev, layout, act, upd)?;
+        }
+
+        /* only report actual changes in sensor state */
+        for link in &dev.services {
+            match link.rtype {
+                RType::Motion => {
+                    let Some(occupancy) = upd.occupancy else {
+                        continue;
+                    };
+
+                    if res.get::<Motion>(link)?.as_motion_opt() != Some(occupancy) {
+                        res.update::<Motion>(&link.rid, |motion| motion.report_motion(occupancy))?;
+                    }
+                }
+                RType::LightLevel => {
+                    let Some(lux) = upd.lux() else {
+                        continue;
+                    };
+
+                    let level = LightLevel::light_level_from_lux(lux);
+                    if res.get::<LightLevel>(link)?.as_light_level_opt() != Some(level) {
+                        res.update::<LightLevel>(&link.rid, |ll| ll.report_light_level(level))?;
+                    }
+                }
+                RType::Temperature => {
+                    let Some(temp) = upd.temperature else {
+                        continue;
+                    };
+
+                    if res.get::<Temperature>(link)?.as_temperature_opt() != Some(temp) {
+                        res.update::<Temperature>(&link.rid, |t| t.report_temperature(temp))?;
+                    }
+                }
+                RType::DevicePower => {
+                    if upd.battery.is_none() && upd.battery_low.is_none() {
+                        continue;
+                    }
+
+                    let state = PowerState::from_battery(upd.battery_level(), upd.battery_low);
+                    if res.get::<DevicePower>(link)?.power_state != state {
+                        res.update::<DevicePower>(&link.rid, |dp| dp.power_state = state)?;
+                    }
+                }
+                _ => {}
             }
+        }
+        drop(res);
+
+        Ok(())
+    }
 
-            if let Some(b) = upd.brightness {
-                glight.dimming = Some(DimmingUpdate {
-                    brightness: b / 254.0 * 100.0,
-                });
+    fn handle_switch_action(
+        &self,
+        res: &mut Resources,
+        dev: &Device,
+        layout: &SwitchLayout,
+        act: &str,
+        upd: &DeviceUpdate,
+    ) -> ApiResult<()> {
+        if let Some(direction) = layout.rotary.and_then(|kind| kind.parse_action(act)) {
+            let rotation = Rotation {
+                direction,
+                steps: upd.action_steps(),
+                duration: upd.action_duration_ms(),
+            };
+
+            if let Some(link) = dev
+                .services
+                .iter()
+                .find(|rl| rl.rtype == RType::RelativeRotary)
+            {
+                res.update::<RelativeRotary>(&link.rid, |rr| rr.report_rotation(rotation))?;
             }
-        })
+            return Ok(());
+        }
+
+        let Some((name, event)) = action::parse_button_action(act) else {
+            return Ok(());
+        };
+
+        let link = layout
+            .buttons
+            .iter()
+            .position(|btn| btn == name)
+            .and_then(|idx| {
+                dev.services
+                    .iter()
+                    .filter(|rl| rl.rtype == RType::Button)
+                    .nth(idx)
+            });
+
+        if let Some(link) = link {
+            res.update::<Button>(&link.rid, |btn| btn.report_event(event))?;
+        } else {
+            log::debug!("[{}] Unknown button action {act:?}", self.name);
+        }
+
+        Ok(())
     }
 
     async fn handle_bridge_message(&mut self, msg: Message) -> ApiResult<()> {
         #[allow(unused_variables)]
         match msg {
-            Message::BridgeInfo(ref obj) => { /* println!("{obj:#?}"); */ }
-            Message::BridgeLogging(ref obj) => { /* println!("{obj:#?}"); */ }
+            Message::BridgeInfo(ref obj) => self.handle_bridge_info(obj).await?,
+            Message::BridgeLogging(ref obj) => {
+                if let Some((topic, err)) = obj.publish_failure() {
+                    log::warn!("[{}] Request to {topic} failed: {err}", self.name);
+                    let key = AckKey::Topic(topic.to_string());
+                    self.ack_resolve(&key, &Err(err.to_string()));
+                }
+            }
             Message::BridgeExtensions(ref obj) => { /* println!("{obj:#?}"); */ }
-            Message::BridgeEvent(ref obj) => { /* println!("{obj:#?}"); */ }
+            Message::BridgeEvent(ref obj) => {
+                if let Err(err) = self.handle_bridge_event(obj).await {
+                    log::error!("[{}] Failed to handle bridge event: {err}", self.name);
+                }
+            }
             Message::BridgeDefinitions(ref obj) => { /* println!("{obj:#?}"); */ }
             Message::BridgeState(ref obj) => { /* println!("{obj:#?}"); */ }
             Message::BridgeConverters(ref obj) => { /* println!("{obj:#?}"); */ }
@@ -459,16 +891,16 @@ impl Z2mBackend {
                             dev.model_id.as_deref().unwrap_or("<unknown model>")
                         );
                         self.add_light(dev, exp).await?;
-                    } else {
-                        log::debug!(
-                            "[{}] Ignoring unsupported device {}",
+                    } else if dev.expose_sensor() {
+                        log::info!(
+                            "[{}] Adding sensor {:?}: [{}] ({})",
                             self.name,
-                            dev.friendly_name
+                            dev.ieee_address,
+                            dev.friendly_name,
+                            dev.model_id.as_deref().unwrap_or("<unknown model>")
                         );
-                        self.ignore.insert(dev.friendly_name.to_string());
-                    }
-                    /*
-                    if dev.expose_action() {
+                        self.add_sensor(dev).await?;
+                    } else if dev.expose_action() {
                         log::info!(
                             "[{}] Adding switch {:?}: [{}] ({})",
                             self.name,
@@ -477,25 +909,285 @@ impl Z2mBackend {
                             dev.model_id.as_deref().unwrap_or("<unknown model>")
                         );
                         self.add_switch(dev).await?;
+                    } else {
+                        log::debug!(
+                            "[{}] Ignoring unsupported device {}",
+                            self.name,
+                            dev.friendly_name
+                        );
+                        self.ignore.insert(dev.friendly_name.to_string());
+                    }
+                }
+                self.remove_stale_devices(obj).await?;
+                self.seen_devices = true;
+                self.report_synced().await?;
+            }
+
+            Message::BridgeGroups(ref obj) => {
+                /* println!("{obj:#?}"); */
+                for grp in obj {
+                    self.add_group(grp).await?;
+                }
+                self.remove_stale_groups(obj).await?;
+                self.seen_groups = true;
+                self.report_synced().await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Follow the z2m permit join state, which is reported as the zigbee
+    /// device discovery status
+    async fn handle_bridge_info(&mut self, info: &BridgeInfo) -> ApiResult<()> {
+        /* the status is persisted, so always sync it on the first update */
+        if self.permit_join == Some(info.permit_join) {
+            return Ok(());
+        }
+        self.permit_join = Some(info.permit_join);
+
+        let status = if info.permit_join {
+            ZigbeeDeviceDiscoveryStatus::Active
+        } else {
+            ZigbeeDeviceDiscoveryStatus::Ready
+        };
+
+        log::info!(
+            "[{}] Permit join is now {}",
+            self.name,
+            if info.permit_join { "open" } else { "closed" }
+        );
+
+        let mut res = self.state.lock().await;
+        for rec in res.get_resources_by_type(RType::ZigbeeDeviceDiscovery) {
+            res.update::<ZigbeeDeviceDiscovery>(&rec.id, |zbdd| zbdd.status = status)?;
+        }
+        drop(res);
+
+        Ok(())
+    }
+
+    async fn handle_bridge_event(&mut self, evt: &BridgeEvent) -> ApiResult<()> {
+        match evt {
+            BridgeEvent::DeviceJoined(dev) => {
+                log::info!(
+                    "[{}] Device joined: {} ({:?})",
+                    self.name,
+                    dev.friendly_name,
+                    dev.ieee_address
+                );
+            }
+            BridgeEvent::DeviceAnnounce(dev) => {
+                log::debug!("[{}] Device announced: {}", self.name, dev.friendly_name);
+            }
+            /* new devices are added once z2m publishes the updated bridge/devices */
+            BridgeEvent::DeviceInterview(intv) => match intv.status {
+                InterviewStatus::Started => {
+                    log::info!("[{}] Interviewing {}..", self.name, intv.friendly_name);
+                }
+                InterviewStatus::Successful => {
+                    log::info!("[{}] Interview of {} done", self.name, intv.friendly_name);
+                    if intv.supported == Some(false) {
+                        log::warn!(
+                            "[{}] Device {} is not supported by z2m",
+                            self.name,
+                            intv.friendly_name
+                        );
                     }
-                    */
                 }
+                InterviewStatus::Failed => {
+                    log::warn!("[{}] Interview of {} failed", self.name, intv.friendly_name);
+                }
+            },
+            BridgeEvent::DeviceLeave(dev) => {
+                log::info!(
+                    "[{}] Device {} left the network",
+                    self.name,
+                    dev.friendly_name
+                );
+                let link_device = RType::Device.deterministic(&dev.ieee_address);
+                self.remove_device(&dev.friendly_name, link_device).await?;
+            }
+            BridgeEvent::DeviceRenamed(ren) => self.rename_device(&ren.from, &ren.to).await?,
+            BridgeEvent::Unknown(_) => {}
+        }
+
+        Ok(())
+    }
+
+    /// Find the z2m topic of a device. Lights are mapped by their light
+    /// service, other devices by the device itself.
+    fn device_topic(&self, dev: &Device, link: &ResourceLink) -> ApiResult<String> {
+        iter::once(link)
+            .chain(&dev.services)
+            .find_map(|svc| self.rmap.get(&svc.rid))
+            .cloned()
+            .ok_or(ApiError::NotFound(link.rid))
+    }
+
+    /// Find the z2m topics of group members, which are devices (in rooms) or
+    /// lights (in zones)
+    fn member_topics(&self, res: &Resources, members: &[ResourceLink]) -> ApiResult<Vec<String>> {
+        members
+            .iter()
+            .map(|member| {
+                let link = match member.rtype {
+                    RType::Light => res.get::<Light>(member)?.owner,
+                    _ => *member,
+                };
+                self.device_topic(res.get::<Device>(&link)?, &link)
+            })
+            .collect()
+    }
+
+    /// Tell [`Resources`] once the full device and group lists are imported,
+    /// so stored resources from devices and groups z2m no longer has can be
+    /// cleaned up
+    async fn report_synced(&self) -> ApiResult<()> {
+        if !(self.seen_devices && self.seen_groups) {
+            return Ok(());
+        }
+
+        self.state
+            .lock()
+            .await
+            .backend_synced(&self.name, self.config.bifrost.orphans)
+    }
+
+    /// Delete the devices that z2m no longer knows about, e.g. because they
+    /// were removed while the connection was down
+    async fn remove_stale_devices(&mut self, devices: &[api::Device]) -> ApiResult<()> {
+        let topics: HashSet<&str> = devices
+            .iter()
+            .map(|dev| dev.friendly_name.as_str())
+            .collect();
+
+        self.ignore.retain(|topic| topics.contains(topic.as_str()));
+
+        let res = self.state.lock().await;
+        let stale: Vec<(String, ResourceLink)> = self
+            .map
+            .iter()
+            .filter(|(topic, _)| !topics.contains(topic.as_str()))
+            .filter_map(|(topic, uuid)| {
+                let link_device = match res.get_resource_by_id(uuid).ok()?.obj {
+                    Resource::Device(_) => RType::Device.link_to(*uuid),
+                    Resource::Light(light) => light.owner,
+                    _ => return None,
+                };
+                Some((topic.clone(), link_device))
+            })
+            .collect();
+        drop(res);
+
+        for (topic, link_device) in stale {
+            log::info!("[{}] Device {topic} is gone from z2m", self.name);
+            self.remove_device(&topic, link_device).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Forget the device known as `topic`, and delete it along with its services
+    async fn remove_device(&mut self, topic: &str, link_device: ResourceLink) -> ApiResult<()> {
+        self.ignore.remove(topic);
+
+        let Some(uuid) = self.map.remove(topic) else {
+            return Ok(());
+        };
+        self.rmap.remove(&uuid);
+        self.refreshed.remove(&uuid);
+        self.switches.remove(&link_device.rid);
+
+        log::info!("[{}] Removing device {topic}", self.name);
+
+        let mut res = self.state.lock().await;
+        if res.get::<Device>(&link_device).is_ok() {
+            res.delete(&link_device)?;
+        }
+        drop(res);
+
+        Ok(())
+    }
+
+    /// Follow a device rename in z2m, which changes the mqtt topic of the device
+    async fn rename_device(&mut self, from: &str, to: &str) -> ApiResult<()> {
+        let Some(uuid) = self.map.remove(from) else {
+            if self.ignore.remove(from) {
+                self.ignore.insert(to.to_string());
             }
+            return Ok(());
+        };
+
+        log::info!("[{}] Device {from} renamed to {to}", self.name);
+
+        self.map.insert(to.to_string(), uuid);
+        self.rmap.insert(uuid, to.to_string());
+
+        let mut res = self.state.lock().await;
+        let obj = res.get_resource_by_id(&uuid)?.obj;
+        let link = ResourceLink::new(uuid, obj.rtype());
+
+        let aux = res.aux_get(&link).cloned().unwrap_or_default();
+        res.aux_set(&link, aux.with_topic(to));
+
+        let link_device = match obj {
+            Resource::Light(light) => {
+                res.update::<Light>(&uuid, |light| light.metadata.name = to.to_string())?;
+                light.owner
+            }
+            Resource::Device(_) => link,
+            _ => return Ok(()),
+        };
+
+        res.update::<Device>(&link_device.rid, |dev| dev.metadata.name = to.to_string())?;
+        drop(res);
+
+        Ok(())
+    }
 
-            Message::BridgeGroups(ref obj) => {
-                /* println!("{obj:#?}"); */
-                for grp in obj {
-                    self.add_group(grp).await?;
-                }
-            }
+    async fn handle_availability(&self, uuid: &Uuid, payload: &Value) -> ApiResult<()> {
+        let avail = AvailabilityPayload::deserialize(payload)?;
+        let status = ZigbeeConnectivityStatus::from(avail.state() == Availability::Online);
+
+        let mut res = self.state.lock().await;
+        let dev = match res.get_resource_by_id(uuid)?.obj {
+            Resource::Device(dev) => dev,
+            Resource::Light(light) => res.get::<Device>(&light.owner)?.clone(),
+            _ => return Ok(()),
+        };
+
+        let Some(link) = dev.zigbee_connectivity_service() else {
+            return Ok(());
+        };
+
+        if res.get::<ZigbeeConnectivity>(link)?.status != status {
+            log::info!(
+                "[{}] Device {} is now {:?}",
+                self.name,
+                dev.metadata.name,
+                avail.state()
+            );
+            res.update::<ZigbeeConnectivity>(&link.rid, |zbc| zbc.status = status)?;
         }
+        drop(res);
+
         Ok(())
     }
 
     async fn handle_device_message(&mut self, msg: RawMessage) -> ApiResult<()> {
-        if msg.topic.ends_with("/availability") || msg.topic.ends_with("/action") {
-            // availability: https://www.zigbee2mqtt.io/guide/usage/mqtt_topics_and_messages.html#zigbee2mqtt-friendly-name-availability
-            // action: https://www.home-assistant.io/integrations/device_trigger.mqtt/
+        // availability: https://www.zigbee2mqtt.io/guide/usage/mqtt_topics_and_messages.html#zigbee2mqtt-friendly-name-availability
+        if let Some(topic) = msg.topic.strip_suffix("/availability") {
+            if let Some(uuid) = self.map.get(topic).copied() {
+                if let Err(err) = self.handle_availability(&uuid, &msg.payload).await {
+                    log::error!("[{}] Cannot parse availability: {err}", self.name);
+                }
+            }
+            return Ok(());
+        }
+
+        // action: https://www.home-assistant.io/integrations/device_trigger.mqtt/
+        // (the same action is also reported in the device state, which is handled below)
+        if msg.topic.ends_with("/action") {
             return Ok(());
         }
 
@@ -540,12 +1232,68 @@ impl Z2mBackend {
             err
         })?;
 
+        self.handle_message(msg).await
+    }
+
+    async fn handle_bridge_response(&mut self, endpoint: &str, payload: &Value) -> ApiResult<()> {
+        let resp = BridgeResponse::deserialize(payload)?;
+
+        let res = resp.failure().map_or(Ok(()), |err| {
+            log::warn!("[{}] Request {endpoint} failed: {err}", self.name);
+            Err(err.to_string())
+        });
+
+        /* z2m does not announce removed or renamed devices, so follow up once it confirms */
+        match endpoint {
+            _ if res.is_err() => {}
+            "device/remove" => {
+                if let Some(topic) = resp.data.get("id").and_then(Value::as_str) {
+                    if let Some(link) = self.removing.remove(topic) {
+                        self.remove_device(topic, link).await?;
+                    }
+                }
+            }
+            "device/rename" => {
+                let from = resp.data.get("from").and_then(Value::as_str);
+                let to = resp.data.get("to").and_then(Value::as_str);
+                if let (Some(from), Some(to)) = (from, to) {
+                    self.rename_device(from, to).await?;
+                }
+            }
+            _ => {}
+        }
+
+        if let Some(transaction) = resp.transaction() {
+            let key = AckKey::Transaction(transaction.to_string());
+
+            /* rooms are added before their group is created, so undo that on failure */
+            if let Some(link) = self.creating.remove(&key) {
+                if res.is_err() {
+                    log::warn!(
+                        "[{}] Deleting {link:?}, since its group was not created",
+                        self.name
+                    );
+                    self.state.lock().await.delete(&link)?;
+                }
+            }
+
+            self.ack_batch_reply(&key, res);
+        }
+
+        Ok(())
+    }
+
+    async fn handle_message(&mut self, msg: RawMessage) -> ApiResult<()> {
         /* bridge messages are handled differently. everything else is a device message */
         if !msg.topic.starts_with("bridge/") {
             return self.handle_device_message(msg).await;
         }
 
-        match serde_json::from_str(&txt) {
+        if let Some(endpoint) = msg.topic.strip_prefix("bridge/response/") {
+            return self.handle_bridge_response(endpoint, &msg.payload).await;
+        }
+
+        match serde_json::to_value(&msg).and_then(Message::deserialize) {
             Ok(bridge_msg) => self.handle_bridge_message(bridge_msg).await,
             Err(err) => {
                 match msg.topic.as_str() {
@@ -618,18 +1366,95 @@ impl Z2mBackend {
         Ok(())
     }
 
-    async fn websocket_send(
+    /// Store each action of a scene on its light, using `scene_add`
+    async fn store_scene_actions(
+        &self,
+        sink: &mut impl Z2mSink,
+        scene: &Scene,
+        index: u32,
+        group_id: u32,
+    ) -> ApiResult<Option<AckKey>> {
+        let transition = scene
+            .recall
+            .duration
+            .map_or(0.0, |ms| f64::from(ms) / 1000.0);
+
+        let mut key = None;
+        for act in &scene.actions {
+            let Some(topic) = self.rmap.get(&act.target.rid) else {
+                continue;
+            };
+
+            let state = Self::scene_action_state(&act.action).with_transition(Some(transition));
+
+            let z2mreq = Z2mRequest::SceneAdd {
+                id: index,
+                group_id,
+                name: &scene.metadata.name,
+                state: &state,
+            };
+            key = self.send_request(sink, topic, z2mreq).await?.or(key);
+        }
+
+        Ok(key)
+    }
+
+    /// Recall a scene by sending each light its action, using the transition
+    /// time from `recall.duration`, and scaling brightness by `recall.dimming`
+    async fn recall_scene_actions(
+        &self,
+        sink: &mut impl Z2mSink,
+        scene: &Scene,
+        recall: &SceneRecall,
+    ) -> ApiResult<Option<AckKey>> {
+        let transition = recall.duration.map(|ms| f64::from(ms) / 1000.0);
+
+        let mut key = None;
+        for act in &scene.actions {
+            let Some(topic) = self.rmap.get(&act.target.rid) else {
+                continue;
+            };
+
+            let mut state = Self::scene_action_state(&act.action).with_transition(transition);
+
+            /* lights that are off in the scene stay off */
+            if let Some(dim) = recall.dimming {
+                if state.state != Some(DeviceState::Off) {
+                    let brightness = act.action.dimming.map_or(100.0, |d| d.brightness);
+                    let scaled = brightness * dim.brightness / 100.0;
+                    state = state.with_brightness(Some(scaled / 100.0 * 254.0));
+                }
+            }
+
+            let z2mreq = Z2mRequest::Update(&state);
+            key = self.send_request(sink, topic, z2mreq).await?.or(key);
+        }
+
+        Ok(key)
+    }
+
+    /// The device state for a scene action
+    fn scene_action_state(action: &SceneAction) -> DeviceUpdate {
+        DeviceUpdate::default()
+            .with_state(action.on.map(|on| on.on))
+            .with_brightness(action.dimming.map(|dim| dim.brightness / 100.0 * 254.0))
+            .with_color_temp(action.color_temperature.map(|ct| ct.mirek))
+            .with_color_xy(action.color.map(|col| col.xy))
+    }
+
+    /// Send a device request, returning the key its outcome is reported under
+    async fn send_request(
         &self,
-        socket: &mut WebSocketStream<MaybeTlsStream<TcpStream>>,
+        sink: &mut impl Z2mSink,
         topic: &str,
         payload: Z2mRequest<'_>,
-    ) -> ApiResult<()> {
+    ) -> ApiResult<Option<AckKey>> {
         let Some(uuid) = self.map.get(topic) else {
             log::trace!(
                 "[{}] Topic [{topic}] unknown on this z2m connection",
                 self.name
             );
-            return Ok(());
+            return Ok(None);
         };
 
         log::trace!(
@@ -648,23 +1473,155 @@ impl Z2mBackend {
                 payload: serde_json::to_value(payload)?,
             }
         };
-        let json = serde_json::to_string(&api_req)?;
-        log::debug!("[{}] Sending {json}", self.name);
-        let msg = tungstenite::Message::text(json);
-        Ok(socket.send(msg).await?)
+        log::debug!(
+            "[{}] Sending {}",
+            self.name,
+            serde_json::to_string(&api_req)?
+        );
+        sink.send_message(api_req).await?;
+
+        Ok(Some(AckKey::Topic(topic.to_string())))
+    }
+
+    /// Send a request to the z2m bridge (`bridge/request/<endpoint>`).
+    ///
+    /// The request is tagged with a fresh transaction id, which z2m includes
+    /// in its reply on `bridge/response/<endpoint>`.
+    pub async fn send_bridge_request(
+        &self,
+        sink: &mut impl Z2mSink,
+        endpoint: &str,
+        mut payload: Value,
+    ) -> ApiResult<AckKey> {
+        let transaction = format!("bifrost-{}", Uuid::new_v4().simple());
+
+        if let Value::Object(map) = &mut payload {
+            map.insert("transaction".to_string(), json!(transaction));
+        }
+
+        let api_req = RawMessage {
+            topic: format!("bridge/request/{endpoint}"),
+            payload,
+        };
+        log::debug!(
+            "[{}] Sending {}",
+            self.name,
+            serde_json::to_string(&api_req)?
+        );
+        sink.send_message(api_req).await?;
+
+        Ok(AckKey::Transaction(transaction))
+    }
+
+    /// Forget acks that z2m never answered. Dropping the reply channel makes
+    /// the waiting side consider the request successful.
+    fn ack_cleanup(&mut self) {
+        let now = Utc::now();
+        self.pending.retain(|_, acks| {
+            acks.retain(|ack| ack.expire > now);
+            !acks.is_empty()
+        });
+        self.batches.retain(|_, batch| batch.expire > now);
+    }
+
+    /// Track the bridge requests sent for a single backend request, returning
+    /// the key its outcome is reported under
+    fn ack_batch(&mut self, keys: Vec<AckKey>) -> Option<AckKey> {
+        let first = keys.first()?.clone();
+
+        if keys.len() > 1 {
+            let batch = AckBatch {
+                expire: Utc::now() + BackendAck::TIMEOUT,
+                waiting: keys.into_iter().collect(),
+                res: Ok(()),
+            };
+            self.batches.insert(first.clone(), batch);
+        }
+
+        Some(first)
+    }
+
+    /// Record the reply for `key`, and report the outcome once every request
+    /// in its batch (if any) has replied
+    fn ack_batch_reply(&mut self, key: &AckKey, res: Result<(), String>) {
+        let Some(batch_key) = self
+            .batches
+            .iter()
+            .find(|(_, batch)| batch.waiting.contains(key))
+            .map(|(batch_key, _)| batch_key.clone())
+        else {
+            return self.ack_resolve(key, &res);
+        };
+
+        let Some(batch) = self.batches.get_mut(&batch_key) else {
+            return;
+        };
+
+        batch.waiting.remove(key);
+        if batch.res.is_ok() {
+            batch.res = res;
+        }
+
+        if batch.waiting.is_empty() {
+            if let Some(batch) = self.batches.remove(&batch_key) {
+                self.ack_resolve(&batch_key, &batch.res);
+            }
+        }
+    }
+
+    /// Report the outcome of all pending requests for `key`
+    fn ack_resolve(&mut self, key: &AckKey, res: &Result<(), String>) {
+        for ack in self.pending.remove(key).unwrap_or_default() {
+            let _ = ack
+                .reply
+                .send(res.clone().map_err(ApiError::Z2mRequestFailed));
+        }
+    }
+
+    /// Send the next queued request, and track the replies to it
+    async fn send_next_request(&mut self, sink: &mut impl Z2mSink) {
+        self.ack_cleanup();
+
+        let Some((req, replies)) = self.queue.pop() else {
+            return;
+        };
+
+        match self.handle_backend_request(sink, req).await {
+            Ok(Some(key)) => {
+                let expire = Utc::now() + BackendAck::TIMEOUT;
+                self.pending.entry(key).or_default().extend(
+                    replies
+                        .into_iter()
+                        .map(|reply| PendingAck { expire, reply }),
+                );
+            }
+            Ok(None) => {
+                for reply in replies {
+                    let _ = reply.send(Ok(()));
+                }
+            }
+            Err(err) => {
+                log::error!("[{}] Backend request failed: {err}", self.name);
+                for reply in replies {
+                    let _ = reply.send(Err(ApiError::BackendRequestFailed(err.to_string())));
+                }
+            }
+        }
     }
 
+    /// Handle a request, returning the key its outcome is reported under (if
+    /// anything was sent to z2m)
     #[allow(clippy::too_many_lines)]
-    async fn websocket_write(
+    async fn handle_backend_request(
         &mut self,
-        socket: &mut WebSocketStream<MaybeTlsStream<TcpStream>>,
-        req: Arc<BackendRequest>,
-    ) -> ApiResult<()> {
+        sink: &mut impl Z2mSink,
+        req: BackendRequest,
+    ) -> ApiResult<Option<AckKey>> {
         self.learn_cleanup();
 
         let mut lock = self.state.lock().await;
 
-        match (*req).clone() {
+        match req {
             BackendRequest::LightUpdate(link, upd) => {
                 if let Some(topic) = self.rmap.get(&link.rid) {
                     // We cannot recover .mode from backend updates, since these only contain
@@ -680,7 +1637,7 @@ impl Z2mBackend {
                     let hue_effects = lock.get::<Light>(&link)?.effects.is_some();
                     drop(lock);
 
-                    if hue_effects {
+                    return if hue_effects {
                         let mut hz = HueZigbeeUpdate::new();
 
                         if let Some(on) = &upd.on {
@@ -773,7 +1730,7 @@ impl Z2mBackend {
                             value: &upd,
                         };
 
-                        self.websocket_send(socket, topic, z2mreq).await?;
+                        self.send_request(sink, topic, z2mreq).await
                     } else {
                         let payload = DeviceUpdate::default()
                             .with_state(upd.on.map(|on| on.on))
@@ -784,8 +1741,32 @@ impl Z2mBackend {
 
                         let z2mreq = Z2mRequest::Update(&payload);
 
-                        self.websocket_send(socket, topic, z2mreq).await?;
-                    }
+                        self.send_request(sink, topic, z2mreq).await
+                    };
+                }
+            }
+            BackendRequest::LightRefresh(link) => {
+                let light = lock.get::<Light>(&link)?;
+
+                let mut payload = json!({"state": ""});
+                if light.dimming.is_some() {
+                    payload["brightness"] = json!("");
+                }
+                if light.color_temperature.is_some() {
+                    payload["color_temp"] = json!("");
+                }
+                if light.color.is_some() {
+                    payload["color"] = json!("");
+                }
+                drop(lock);
+
+                if let Some(topic) = self.rmap.get(&link.rid) {
+                    let msg = RawMessage {
+                        topic: format!("{topic}/get"),
+                        payload,
+                    };
+                    log::trace!("[{}] Reading state of {topic}", self.name);
+                    sink.send_message(msg).await?;
                 }
             }
             BackendRequest::SceneCreate(link_scene, sid, scene) => {
@@ -798,18 +1779,55 @@ impl Z2mBackend {
                             .with_topic(&scene.metadata.name)
                             .with_index(sid),
                     );
+                    let group_id = lock.aux_get(&scene.group).ok().and_then(|aux| aux.index);
+
+                    lock.add(&link_scene, Resource::Scene(scene.clone()))?;
+                    drop(lock);
+
+                    if let (Some(group_id), false) = (group_id, scene.actions.is_empty()) {
+                        return self.store_scene_actions(sink, &scene, sid, group_id).await;
+                    }
+
+                    /* without explicit actions, store the current state of the lights */
                     let z2mreq = Z2mRequest::SceneStore {
-                        name: &scene.metadata.name.clone(),
+                        name: &scene.metadata.name,
                         id: sid,
                     };
 
-                    lock.add(&link_scene, Resource::Scene(scene))?;
-                    drop(lock);
-
-                    self.websocket_send(socket, topic, z2mreq).await?;
+                    return self.send_request(sink, topic, z2mreq).await;
                 }
             }
             BackendRequest::SceneUpdate(link, upd) => {
+                let mut key = None;
+
+                if upd.actions.is_some() {
+                    let scene = lock.get::<Scene>(&link)?.clone();
+                    let index = lock
+                        .aux_get(&link)?
+                        .index
+                        .ok_or(ApiError::NotFound(link.rid))?;
+                    let group_id = lock
+                        .aux_get(&scene.group)?
+                        .index
+                        .ok_or(ApiError::AuxNotFound(scene.group))?;
+                    drop(lock);
+
+                    /* lights no longer in the scene must forget it too */
+                    if let Some(topic) = self.rmap.get(&scene.group.rid).cloned() {
+                        if !scene.actions.is_empty() {
+                            self.editing.insert(link.rid);
+                        }
+                        self.send_request(sink, &topic, Z2mRequest::SceneRemove(index))
+                            .await?;
+                    }
+
+                    key = self
+                        .store_scene_actions(sink, &scene, index, group_id)
+                        .await?;
+
+                    lock = self.state.lock().await;
+                }
+
                 if let Some(recall) = upd.recall {
                     let scene = lock.get::<Scene>(&link)?;
                     if recall.action == Some(SceneStatusUpdate::Active) {
@@ -832,21 +1850,60 @@ impl Z2mBackend {
                             })?;
                         }
 
-                        let room = lock.get::<Scene>(&link)?.group.rid;
+                        let scene = lock.get::<Scene>(&link)?.clone();
                         drop(lock);
 
-                        if let Some(topic) = self.rmap.get(&room).cloned() {
+                        /* z2m cannot adjust a stored scene, so set each light directly */
+                        if recall.duration.is_some() || recall.dimming.is_some() {
+                            if !scene.actions.is_empty() {
+                                return self.recall_scene_actions(sink, &scene, &recall).await;
+                            }
+                            log::warn!(
+                                "[{}] Actions of {link:?} unknown, ignoring recall duration and dimming",
+                                self.name
+                            );
+                        }
+
+                        if let Some(topic) = self.rmap.get(&scene.group.rid).cloned() {
                             self.learn_scene_recall(&link).await?;
                             let z2mreq = Z2mRequest::SceneRecall(index);
-                            self.websocket_send(socket, &topic, z2mreq).await?;
+                            return self.send_request(sink, &topic, z2mreq).await;
                         }
                     } else {
                         log::error!("Scene recall type not supported: {recall:?}");
                     }
                 }
+
+                return Ok(key);
             }
             BackendRequest::GroupedLightUpdate(link, upd) => {
-                let room = lock.get::<GroupedLight>(&link)?.owner.rid;
+                let owner = lock.get::<GroupedLight>(&link)?.owner;
+
+                /* the bridge home has no z2m group, so queue an update for each
+                 * light, paced like any other request. Failures for single
+                 * lights are not reported back. */
+                if owner.rtype == RType::BridgeHome {
+                    let lights: Vec<ResourceLink> = self
+                        .map
+                        .values()
+                        .map(|uuid| RType::Light.link_to(*uuid))
+                        .filter(|light| lock.get::<Light>(light).is_ok())
+                        .collect();
+                    drop(lock);
+
+                    for light in lights {
+                        let (reply, _) = oneshot::channel();
+                        self.queue.push(QueuedRequest {
+                            req: BackendRequest::LightUpdate(light, LightUpdate::from(&upd)),
+                            reply,
+                        });
+                    }
+                    return Ok(None);
+                }
+
+                let Some(topic) = self.rmap.get(&owner.rid).cloned() else {
+                    return Ok(None);
+                };
                 drop(lock);
 
                 let payload = DeviceUpdate::default()
@@ -855,15 +1912,172 @@ impl Z2mBackend {
                     .with_color_temp(upd.color_temperature.map(|ct| ct.mirek))
                     .with_color_xy(upd.color.map(|col| col.xy));
 
-                if let Some(topic) = self.rmap.get(&room) {
-                    let z2mreq = Z2mRequest::Update(&payload);
-                    self.websocket_send(socket, topic, z2mreq).await?;
+                let z2mreq = Z2mRequest::Update(&payload);
+                return self.send_request(sink, &topic, z2mreq).await;
+            }
+            BackendRequest::ZigbeeDeviceDiscoveryUpdate(_link, upd) => {
+                drop(lock);
+
+                let Some(action) = upd.action else {
+                    return Ok(None);
+                };
+
+                if !action.search_codes.is_empty() {
+                    log::warn!(
+                        "[{}] Searching by install code is not supported, ignoring search codes",
+                        self.name
+                    );
+                }
+
+                log::info!(
+                    "[{}] Permitting devices to join for {} seconds",
+                    self.name,
+                    Self::PERMIT_JOIN_TIME
+                );
+
+                let payload = json!({"value": true, "time": Self::PERMIT_JOIN_TIME});
+                let key = self
+                    .send_bridge_request(sink, "permit_join", payload)
+                    .await?;
+                return Ok(Some(key));
+            }
+            BackendRequest::RoomCreate(
+                link,
+                Room {
+                    children, metadata, ..
+                },
+            )
+            | BackendRequest::ZoneCreate(
+                link,
+                Zone {
+                    children, metadata, ..
+                },
+            ) => {
+                let prefix = self.server.group_prefix.as_deref().unwrap_or_default();
+                let topic = format!("{prefix}{}", metadata.name);
+
+                let members = self.member_topics(&lock, &children)?;
+
+                lock.aux_set(&link, AuxData::new().with_topic(&topic));
+                lock.set_owner(&link, &self.name);
+                drop(lock);
+
+                log::info!("[{}] Creating group {topic}", self.name);
+
+                let payload = json!({"friendly_name": topic});
+                let key = self.send_bridge_request(sink, "group/add", payload).await?;
+                self.creating.insert(key.clone(), link);
+
+                let mut keys = vec![key];
+                for device in members {
+                    let payload = json!({"group": topic, "device": device});
+                    let key = self
+                        .send_bridge_request(sink, "group/members/add", payload)
+                        .await?;
+                    keys.push(key);
+                }
+
+                return Ok(self.ack_batch(keys));
+            }
+            BackendRequest::RoomUpdate(link, upd) | BackendRequest::ZoneUpdate(link, upd) => {
+                let Some(children) = upd.children else {
+                    return Ok(None);
+                };
+
+                let topic = self
+                    .rmap
+                    .get(&link.rid)
+                    .cloned()
+                    .ok_or(ApiError::NotFound(link.rid))?;
+
+                let current = match lock.get_resource_by_id(&link.rid)?.obj {
+                    Resource::Room(room) => room.children,
+                    Resource::Zone(zone) => zone.children,
+                    obj => return Err(ApiError::WrongType(link.rtype, obj.rtype())),
+                };
+
+                let added: Vec<_> = children
+                    .iter()
+                    .filter(|member| !current.contains(member))
+                    .copied()
+                    .collect();
+                let removed: Vec<_> = current
+                    .iter()
+                    .filter(|member| !children.contains(member))
+                    .copied()
+                    .collect();
+
+                let changes = [
+                    ("group/members/add", self.member_topics(&lock, &added)?),
+                    ("group/members/remove", self.member_topics(&lock, &removed)?),
+                ];
+                drop(lock);
+
+                /* z2m publishes the new members, which updates the room or zone */
+                let mut keys = vec![];
+                for (endpoint, devices) in changes {
+                    for device in devices {
+                        log::info!("[{}] Request {endpoint} for {device} in {topic}", self.name);
+                        let payload = json!({"group": topic, "device": device});
+                        keys.push(self.send_bridge_request(sink, endpoint, payload).await?);
+                    }
                 }
+
+                return Ok(self.ack_batch(keys));
+            }
+            BackendRequest::Delete(link) if matches!(link.rtype, RType::Room | RType::Zone) => {
+                drop(lock);
+
+                let topic = self
+                    .rmap
+                    .get(&link.rid)
+                    .cloned()
+                    .ok_or(ApiError::NotFound(link.rid))?;
+
+                log::info!("[{}] Removing group {topic}", self.name);
+
+                let payload = json!({"id": topic});
+                let key = self
+                    .send_bridge_request(sink, "group/remove", payload)
+                    .await?;
+                return Ok(Some(key));
+            }
+            BackendRequest::DeviceUpdate(link, upd) => {
+                let topic = self.device_topic(lock.get::<Device>(&link)?, &link)?;
+                drop(lock);
+
+                let name = upd.metadata.and_then(|md| md.name);
+                let Some(name) = name.filter(|name| self.server.sync_names && *name != topic)
+                else {
+                    return Ok(None);
+                };
+
+                log::info!("[{}] Renaming device {topic} to {name}", self.name);
+
+                let payload = json!({"from": topic, "to": name});
+                let key = self
+                    .send_bridge_request(sink, "device/rename", payload)
+                    .await?;
+                return Ok(Some(key));
+            }
+            BackendRequest::DeviceDelete(link, force) => {
+                let topic = self.device_topic(lock.get::<Device>(&link)?, &link)?;
+                drop(lock);
+
+                log::info!("[{}] Removing device {topic} from the network", self.name);
+
+                let payload = json!({"id": topic, "force": force});
+                let key = self
+                    .send_bridge_request(sink, "device/remove", payload)
+                    .await?;
+                self.removing.insert(topic, link);
+                return Ok(Some(key));
             }
             BackendRequest::Delete(link) => {
                 if link.rtype != RType::Scene {
-                    return Ok(());
+                    return Ok(None);
                 }
+                self.editing.remove(&link.rid);
 
                 let room = lock.get::<Scene>(&link)?.group.rid;
                 let index = lock
@@ -874,25 +2088,127 @@ impl Z2mBackend {
 
                 if let Some(topic) = self.rmap.get(&room) {
                     let z2mreq = Z2mRequest::SceneRemove(index);
-                    self.websocket_send(socket, topic, z2mreq).await?;
+                    return self.send_request(sink, topic, z2mreq).await;
                 }
             }
         }
 
+        Ok(None)
+    }
+
+    /// Accept a request from the hue api. While disconnected, the request is
+    /// queued or rejected, depending on the configured policy.
+    fn accept_request(&mut self, queued: QueuedRequest) {
+        if self.online || self.server.offline_requests == OfflinePolicy::Queue {
+            self.queue.push(queued);
+        } else {
+            let _ = queued
+                .reply
+                .send(Err(ApiError::BackendOffline(self.name.clone())));
+        }
+    }
+
+    /// True if there is a queued request, and it can be sent
+    fn ready_to_send(&self) -> bool {
+        self.online && !self.queue.is_empty()
+    }
+
+    /// The zigbee connectivity service of the device behind a topic
+    fn connectivity_service(res: &Resources, uuid: &Uuid) -> Option<ResourceLink> {
+        let dev = match res.get_resource_by_id(uuid).ok()?.obj {
+            Resource::Device(dev) => dev,
+            Resource::Light(light) => res.get::<Device>(&light.owner).ok()?.clone(),
+            _ => return None,
+        };
+        dev.zigbee_connectivity_service().copied()
+    }
+
+    /// Report the devices of this server as unreachable while the connection
+    /// to z2m is down
+    pub async fn set_offline(&mut self) -> ApiResult<()> {
+        if !self.online {
+            return Ok(());
+        }
+        self.online = false;
+
+        log::warn!(
+            "[{}] Connection lost, marking devices as unreachable",
+            self.name
+        );
+
+        if self.server.offline_requests == OfflinePolicy::Fail {
+            for reply in self.queue.drain_replies() {
+                let _ = reply.send(Err(ApiError::BackendOffline(self.name.clone())));
+            }
+        }
+
+        let mut res = self.state.lock().await;
+        for uuid in self.map.values() {
+            let Some(link) = Self::connectivity_service(&res, uuid) else {
+                continue;
+            };
+            if res.get::<ZigbeeConnectivity>(&link)?.status == ZigbeeConnectivityStatus::Connected {
+                res.update::<ZigbeeConnectivity>(&link.rid, |zbc| {
+                    zbc.status = ZigbeeConnectivityStatus::ConnectivityIssue;
+                })?;
+                self.offline_zbc.insert(link.rid);
+            }
+        }
+        drop(res);
+
+        Ok(())
+    }
+
+    /// Restore the devices marked by [`Self::set_offline`]. Devices that are
+    /// really unavailable are marked again once z2m reports their state.
+    pub async fn set_online(&mut self) -> ApiResult<()> {
+        self.online = true;
+        self.refreshed.clear();
+
+        let mut res = self.state.lock().await;
+        for rid in self.offline_zbc.drain() {
+            if res.get_resource_by_id(&rid).is_ok() {
+                res.update::<ZigbeeConnectivity>(&rid, |zbc| {
+                    zbc.status = ZigbeeConnectivityStatus::Connected;
+                })?;
+            }
+        }
+        drop(res);
+
         Ok(())
     }
 
+    /// Wait until the next connection attempt, while still accepting requests
+    async fn offline_wait(
+        &mut self,
+        chan: &mut UnboundedReceiver<QueuedRequest>,
+        delay: std::time::Duration,
+    ) -> ApiResult<()> {
+        let deadline = Instant::now() + delay;
+        loop {
+            select! {
+                pkt = chan.recv() => {
+                    let api_req = pkt.ok_or(ApiError::BackendRequestsClosed)?;
+                    self.accept_request(api_req);
+                },
+                () = sleep_until(deadline) => return Ok(()),
+            }
+        }
+    }
+
     pub async fn event_loop(
         &mut self,
-        chan: &mut Receiver<Arc<BackendRequest>>,
+        chan: &mut UnboundedReceiver<QueuedRequest>,
         mut socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
     ) -> ApiResult<()> {
         loop {
             select! {
                 pkt = chan.recv() => {
-                    let api_req = pkt?;
-                    self.websocket_write(&mut socket, api_req).await?;
-                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                    let api_req = pkt.ok_or(ApiError::BackendRequestsClosed)?;
+                    self.accept_request(api_req);
+                },
+                () = sleep_until(self.queue.next_send()), if self.ready_to_send() => {
+                    self.send_next_request(&mut socket).await;
                 },
                 pkt = socket.next() => {
                     self.websocket_read(pkt.ok_or(ApiError::UnexpectedZ2mEof)??).await?;
@@ -904,7 +2220,7 @@ impl Z2mBackend {
 
 #[async_trait]
 impl Backend for Z2mBackend {
-    async fn run_forever(mut self, mut chan: Receiver<Arc<BackendRequest>>) -> ApiResult<()> {
+    async fn run_forever(mut self, mut chan: UnboundedReceiver<QueuedRequest>) -> ApiResult<()> {
         // let's not include auth tokens in log output
         let sanitized_url = self.server.get_sanitized_url();
         let url = self.server.get_url();
@@ -921,10 +2237,14 @@ impl Backend for Z2mBackend {
             );
         }
 
+        let mut backoff = Backoff::new();
+
         loop {
             log::info!("[{}] Connecting to {}", self.name, &sanitized_url);
             match connect_async(url.as_str()).await {
                 Ok((socket, _)) => {
+                    backoff.reset();
+                    self.set_online().await?;
                     let res = self.event_loop(&mut chan, socket).await;
                     if let Err(err) = res {
                         log::error!("[{}] Event loop broke: {err}", self.name);
@@ -934,7 +2254,15 @@ impl Backend for Z2mBackend {
                     log::error!("[{}] Connect failed: {err:?}", self.name);
                 }
             }
-            sleep(std::time::Duration::from_millis(2000)).await;
+            self.set_offline().await?;
+
+            let delay = backoff.next_delay();
+            log::info!(
+                "[{}] Reconnecting in {:.1}s",
+                self.name,
+                delay.as_secs_f64()
+            );
+            self.offline_wait(&mut chan, delay).await?;
         }
     }
 }
@@ -0,0 +1,344 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Packet, QoS};
+use serde_json::Value;
+use tokio::select;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::{mpsc, Mutex};
+use tokio::time::{sleep, sleep_until};
+
+use crate::backend::z2m::backoff::Backoff;
+use crate::backend::z2m::{Z2mBackend, Z2mSink};
+use crate::backend::{Backend, QueuedRequest};
+use crate::config::{AppConfig, Z2mServer};
+use crate::error::{ApiError, ApiResult};
+use crate::resource::Resources;
+use crate::z2m::api::RawMessage;
+
+/// Zigbee2mqtt backend, connecting directly to the mqtt broker.
+///
+/// This shares all message handling with [`Z2mBackend`], but does not need the
+/// z2m frontend (websocket) to be enabled.
+pub struct Z2mMqttBackend {
+    z2m: Z2mBackend,
+    /// Delay before restarting a broken event loop
+    backoff: Backoff,
+    /// Whether the mqtt client is connected to the broker
+    connected: bool,
+}
+
+/// Connection events forwarded from the mqtt client to the event loop
+enum MqttEvent {
+    Connected,
+    Disconnected,
+    Message(RawMessage),
+}
+
+struct MqttSink {
+    client: AsyncClient,
+    base_topic: String,
+}
+
+#[async_trait]
+impl Z2mSink for MqttSink {
+    async fn send_message(&mut self, msg: RawMessage) -> ApiResult<()> {
+        let topic = format!("{}/{}", self.base_topic, msg.topic);
+        let payload = serde_json::to_vec(&msg.payload)?;
+        Ok(self
+            .client
+            .publish(topic, QoS::AtMostOnce, false, payload)
+            .await?)
+    }
+}
+
+impl Z2mMqttBackend {
+    /// Maximum mqtt packet size. The `bridge/devices` message is sent as a
+    /// single packet, and easily exceeds the rumqttc default of 10kB.
+    const MAX_PACKET_SIZE: usize = 16 * 1024 * 1024;
+
+    const MQTT_PORT: u16 = 1883;
+
+    pub fn new(
+        name: String,
+        server: Z2mServer,
+        config: Arc<AppConfig>,
+        state: Arc<Mutex<Resources>>,
+    ) -> ApiResult<Self> {
+        let z2m = Z2mBackend::new(name, server, config, state)?;
+        Ok(Self {
+            z2m,
+            backoff: Backoff::new(),
+            connected: false,
+        })
+    }
+
+    fn mqtt_options(&self) -> ApiResult<MqttOptions> {
+        let url = &self.z2m.server.url;
+
+        if url.scheme() != "mqtt" {
+            return Err(ApiError::InvalidMqttUrl(format!(
+                "unsupported scheme {:?} (only \"mqtt\" is supported)",
+                url.scheme()
+            )));
+        }
+
+        let host = url
+            .host_str()
+            .ok_or_else(|| ApiError::InvalidMqttUrl(String::from("missing hostname")))?;
+
+        let client_id = format!(
+            "bifrost-{}-{}",
+            crate::hue::bridge_id(self.z2m.config.bridge.mac),
+            self.z2m.name
+        );
+
+        let mut opts = MqttOptions::new(client_id, host, url.port().unwrap_or(Self::MQTT_PORT));
+        opts.set_keep_alive(Duration::from_secs(30));
+        opts.set_max_packet_size(Self::MAX_PACKET_SIZE, Self::MAX_PACKET_SIZE);
+
+        if !url.username().is_empty() {
+            opts.set_credentials(url.username(), url.password().unwrap_or_default());
+        }
+
+        Ok(opts)
+    }
+
+    /// Requests sent to z2m (by us, or anyone else), as opposed to the
+    /// messages z2m publishes. These are not seen on the websocket api.
+    ///
+    /// Friendly names can contain `set` or `get` segments too, so device
+    /// requests are only recognized as `<name>[/<endpoint>]/set[/<attribute>]`
+    /// (or `get`) for a `<name>` that is `known`.
+    fn is_request(topic: &str, known: impl Fn(&str) -> bool) -> bool {
+        if topic.starts_with("bridge/request/") {
+            return true;
+        }
+
+        if known(topic) {
+            return false;
+        }
+
+        ["/set", "/get"].iter().any(|cmd| {
+            topic.match_indices(cmd).any(|(idx, _)| {
+                let target = &topic[..idx];
+                let attr = &topic[idx + cmd.len()..];
+                let attr_ok = attr.is_empty()
+                    || attr
+                        .strip_prefix('/')
+                        .is_some_and(|attr| !attr.contains('/'));
+                let target_ok = known(target)
+                    || target
+                        .rsplit_once('/')
+                        .is_some_and(|(name, _endpoint)| known(name));
+                attr_ok && target_ok
+            })
+        })
+    }
+
+    /// Drive the mqtt connection, forwarding all received messages on `tx`.
+    ///
+    /// The rumqttc event loop reconnects by itself when polled after an error,
+    /// so this only returns when the receiving end of `tx` is gone.
+    async fn mqtt_poll(
+        name: String,
+        base_topic: String,
+        client: AsyncClient,
+        mut eventloop: EventLoop,
+        tx: mpsc::Sender<MqttEvent>,
+    ) {
+        let prefix = format!("{base_topic}/");
+        let mut backoff = Backoff::new();
+        let mut connected = false;
+
+        loop {
+            match eventloop.poll().await {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    log::info!("[{name}] Connected to mqtt broker");
+                    backoff.reset();
+                    connected = true;
+                    if tx.send(MqttEvent::Connected).await.is_err() {
+                        return;
+                    }
+
+                    /* subscriptions do not survive reconnects, so (re)subscribe here.
+                     * friendly names can contain '/', so take the whole tree */
+                    let filter = format!("{base_topic}/#");
+                    if let Err(err) = client.try_subscribe(filter, QoS::AtMostOnce) {
+                        log::error!("[{name}] Failed to subscribe: {err}");
+                    }
+                }
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    let Some(topic) = publish.topic.strip_prefix(&prefix) else {
+                        continue;
+                    };
+
+                    /* skip cleared retained messages */
+                    if publish.payload.is_empty() {
+                        continue;
+                    }
+
+                    /* legacy availability messages are plain strings, not json */
+                    let payload = serde_json::from_slice(&publish.payload).unwrap_or_else(|_| {
+                        Value::String(String::from_utf8_lossy(&publish.payload).into_owned())
+                    });
+
+                    let msg = RawMessage {
+                        topic: topic.to_string(),
+                        payload,
+                    };
+
+                    if tx.send(MqttEvent::Message(msg)).await.is_err() {
+                        return;
+                    }
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    log::error!("[{name}] Mqtt connection error: {err}");
+                    if std::mem::take(&mut connected)
+                        && tx.send(MqttEvent::Disconnected).await.is_err()
+                    {
+                        return;
+                    }
+
+                    let delay = backoff.next_delay();
+                    log::info!("[{name}] Reconnecting in {:.1}s", delay.as_secs_f64());
+                    sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    async fn event_loop(
+        &mut self,
+        chan: &mut UnboundedReceiver<QueuedRequest>,
+        sink: &mut MqttSink,
+        rx: &mut mpsc::Receiver<MqttEvent>,
+    ) -> ApiResult<()> {
+        loop {
+            select! {
+                pkt = chan.recv() => {
+                    let api_req = pkt.ok_or(ApiError::BackendRequestsClosed)?;
+                    self.z2m.accept_request(api_req);
+                },
+                () = sleep_until(self.z2m.queue.next_send()), if self.z2m.ready_to_send() => {
+                    self.z2m.send_next_request(sink).await;
+                },
+                evt = rx.recv() => {
+                    match evt.ok_or(ApiError::UnexpectedZ2mEof)? {
+                        MqttEvent::Connected => {
+                            self.connected = true;
+                            self.backoff.reset();
+                            self.z2m.set_online().await?;
+                        }
+                        MqttEvent::Disconnected => {
+                            self.connected = false;
+                            self.z2m.set_offline().await?;
+                        }
+                        MqttEvent::Message(msg) => {
+                            /* skip our own requests, now that we know which devices exist */
+                            let known = |name: &str| {
+                                self.z2m.map.contains_key(name) || self.z2m.ignore.contains(name)
+                            };
+                            if Self::is_request(&msg.topic, known) {
+                                continue;
+                            }
+
+                            if let Err(err) = self.z2m.handle_message(msg).await {
+                                log::error!("[{}] Failed to handle message: {err}", self.z2m.name);
+                            }
+                        }
+                    }
+                },
+            };
+        }
+    }
+}
+
+#[async_trait]
+impl Backend for Z2mMqttBackend {
+    async fn run_forever(mut self, mut chan: UnboundedReceiver<QueuedRequest>) -> ApiResult<()> {
+        let opts = self.mqtt_options()?;
+        let base_topic = self.z2m.server.get_base_topic().to_string();
+
+        log::info!(
+            "[{}] Connecting to mqtt broker {}:{} (base topic {base_topic})",
+            self.z2m.name,
+            opts.broker_address().0,
+            opts.broker_address().1,
+        );
+
+        let (client, eventloop) = AsyncClient::new(opts, 64);
+        let (tx, mut rx) = mpsc::channel(64);
+
+        tokio::spawn(Self::mqtt_poll(
+            self.z2m.name.clone(),
+            base_topic.clone(),
+            client.clone(),
+            eventloop,
+            tx,
+        ));
+
+        let mut sink = MqttSink { client, base_topic };
+
+        loop {
+            if let Err(err) = self.event_loop(&mut chan, &mut sink, &mut rx).await {
+                log::error!("[{}] Event loop broke: {err}", self.z2m.name);
+                if matches!(
+                    err,
+                    ApiError::UnexpectedZ2mEof | ApiError::BackendRequestsClosed
+                ) {
+                    return Err(err);
+                }
+            }
+            self.z2m.set_offline().await?;
+
+            let delay = self.backoff.next_delay();
+            log::info!(
+                "[{}] Restarting event loop in {:.1}s",
+                self.z2m.name,
+                delay.as_secs_f64()
+            );
+            self.z2m.offline_wait(&mut chan, delay).await?;
+
+            /* the broker connection may have stayed up, without a new connect event */
+            if self.connected {
+                self.z2m.set_online().await?;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::z2m::mqtt::Z2mMqttBackend;
+
+    #[test]
+    fn request_topics() {
+        let names = [
+            "kitchen/lamp",
+            "lamp",
+            "lamp/set/brightness",
+            "living/get/plug",
+        ];
+        let is_request = |topic| Z2mMqttBackend::is_request(topic, |name| names.contains(&name));
+
+        assert!(is_request("bridge/request/group/add"));
+        assert!(is_request("kitchen/lamp/set"));
+        assert!(is_request("kitchen/lamp/get"));
+        assert!(is_request("lamp/set/brightness/set"));
+        assert!(is_request("lamp/get/state"));
+        assert!(is_request("living/get/plug/set"));
+        assert!(is_request("lamp/11/set"));
+
+        assert!(!is_request("kitchen/lamp"));
+        assert!(!is_request("kitchen/lamp/availability"));
+        assert!(!is_request("bridge/devices"));
+        assert!(!is_request("lamp/set/brightness"));
+        assert!(!is_request("living/get/plug"));
+        assert!(!is_request("living/get/plug/availability"));
+        assert!(!is_request("lamp/set/brightness/availability"));
+        assert!(!is_request("unknown/set"));
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::VecDeque;
+use std::mem::discriminant;
+
+use tokio::time::{Duration, Instant};
+
+use crate::backend::{BackendReply, BackendRequest, QueuedRequest};
+
+/// Requests waiting to be sent to z2m, paced to a maximum rate.
+///
+/// While waiting, updates for the same light or grouped light are merged, so a
+/// burst of updates (e.g. dragging a slider in the Hue app) ends up as a single
+/// request with the newest state. Repeated state reads of a light are merged
+/// the same way.
+pub struct RequestQueue {
+    queue: VecDeque<(BackendRequest, Vec<BackendReply>)>,
+    interval: Duration,
+    next_send: Instant,
+}
+
+impl RequestQueue {
+    #[must_use]
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            queue: VecDeque::new(),
+            interval,
+            next_send: Instant::now(),
+        }
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Earliest time the next request may be sent
+    #[must_use]
+    pub const fn next_send(&self) -> Instant {
+        self.next_send
+    }
+
+    const fn coalescable(req: &BackendRequest) -> bool {
+        matches!(
+            req,
+            BackendRequest::LightUpdate(..)
+                | BackendRequest::LightRefresh(..)
+                | BackendRequest::GroupedLightUpdate(..)
+        )
+    }
+
+    /// Merge `req` into `pending`, if both update the same resource.
+    /// Otherwise, `req` is handed back.
+    fn merge(pending: &mut BackendRequest, req: BackendRequest) -> Result<(), BackendRequest> {
+        match (&mut *pending, req) {
+            (BackendRequest::LightUpdate(link, upd), BackendRequest::LightUpdate(rlink, rupd))
+                if *link == rlink =>
+            {
+                *upd += rupd;
+                Ok(())
+            }
+            (
+                BackendRequest::GroupedLightUpdate(link, upd),
+                BackendRequest::GroupedLightUpdate(rlink, rupd),
+            ) if *link == rlink => {
+                *upd += rupd;
+                Ok(())
+            }
+            (BackendRequest::LightRefresh(link), BackendRequest::LightRefresh(rlink))
+                if *link == rlink =>
+            {
+                Ok(())
+            }
+            (_, req) => Err(req),
+        }
+    }
+
+    pub fn push(&mut self, queued: QueuedRequest) {
+        let QueuedRequest { mut req, reply } = queued;
+
+        if Self::coalescable(&req) {
+            /* updates of the same kind (e.g. two different lights) can be
+             * reordered, but nothing can be moved ahead of other requests */
+            for (pending, replies) in self.queue.iter_mut().rev() {
+                if discriminant(pending) != discriminant(&req) {
+                    break;
+                }
+                match Self::merge(pending, req) {
+                    Ok(()) => {
+                        replies.push(reply);
+                        return;
+                    }
+                    Err(unmerged) => req = unmerged,
+                }
+            }
+        }
+
+        self.queue.push_back((req, vec![reply]));
+    }
+
+    /// Drop all waiting requests, handing back their reply channels
+    pub fn drain_replies(&mut self) -> Vec<BackendReply> {
+        self.queue
+            .drain(..)
+            .flat_map(|(_, replies)| replies)
+            .collect()
+    }
+
+    /// Take the next request to send, and hold back the one after that until
+    /// the rate limit allows it
+    pub fn pop(&mut self) -> Option<(BackendRequest, Vec<BackendReply>)> {
+        let next = self.queue.pop_front()?;
+        self.next_send = Instant::now() + self.interval;
+        Some(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::oneshot;
+    use tokio::time::Duration;
+
+    use crate::backend::z2m::queue::RequestQueue;
+    use crate::backend::{BackendRequest, QueuedRequest};
+    use crate::hue::api::{LightUpdate, RType, SceneUpdate};
+
+    fn queued(req: BackendRequest) -> QueuedRequest {
+        let (reply, _) = oneshot::channel();
+        QueuedRequest { req, reply }
+    }
+
+    #[test]
+    fn merge_light_updates() {
+        let mut queue = RequestQueue::new(Duration::ZERO);
+        let light1 = RType::Light.deterministic(1);
+        let light2 = RType::Light.deterministic(2);
+
+        let upd1 = LightUpdate::new().with_brightness(Some(10.0));
+        let upd2 = LightUpdate::new().with_color_temperature(300);
+        let upd3 = LightUpdate::new().with_brightness(Some(50.0));
+
+        queue.push(queued(BackendRequest::LightUpdate(light1, upd1)));
+        queue.push(queued(BackendRequest::LightUpdate(light2, upd2)));
+        queue.push(queued(BackendRequest::LightUpdate(light1, upd3)));
+        assert_eq!(queue.len(), 2);
+
+        let Some((BackendRequest::LightUpdate(link, upd), replies)) = queue.pop() else {
+            panic!("expected light update");
+        };
+        assert_eq!(link, light1);
+        assert_eq!(upd.dimming.map(|dim| dim.brightness), Some(50.0));
+        assert_eq!(replies.len(), 2);
+    }
+
+    #[test]
+    fn merge_light_refresh() {
+        let mut queue = RequestQueue::new(Duration::ZERO);
+        let light1 = RType::Light.deterministic(1);
+        let light2 = RType::Light.deterministic(2);
+
+        queue.push(queued(BackendRequest::LightRefresh(light1)));
+        queue.push(queued(BackendRequest::LightRefresh(light2)));
+        queue.push(queued(BackendRequest::LightRefresh(light1)));
+        assert_eq!(queue.len(), 2);
+    }
+
+    #[test]
+    fn no_merge_across_other_requests() {
+        let mut queue = RequestQueue::new(Duration::ZERO);
+        let light = RType::Light.deterministic(1);
+        let scene = RType::Scene.deterministic(1);
+
+        queue.push(queued(BackendRequest::LightUpdate(
+            light,
+            LightUpdate::new(),
+        )));
+        queue.push(queued(BackendRequest::SceneUpdate(
+            scene,
+            SceneUpdate::new(),
+        )));
+        queue.push(queued(BackendRequest::LightUpdate(
+            light,
+            LightUpdate::new(),
+        )));
+        assert_eq!(queue.len(), 3);
+    }
+}
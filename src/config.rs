@@ -1,3 +1,4 @@
+use std::time::Duration;
 use std::{collections::HashMap, net::Ipv4Addr};
 
 use camino::{Utf8Path, Utf8PathBuf};
@@ -20,10 +21,26 @@ pub struct BridgeConfig {
     pub timezone: String,
 }
 
+/// What to do with stored resources that no z2m server knows about anymore
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OrphanPolicy {
+    /// Delete the resources
+    #[default]
+    Delete,
+    /// Hide the resources, but keep them in the state file, so they are
+    /// restored (with their names, scenes, etc) if they show up again
+    Quarantine,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct BifrostConfig {
     pub state_file: Utf8PathBuf,
     pub cert_file: Utf8PathBuf,
+    #[serde(default)]
+    pub wait_for_ack: bool,
+    #[serde(default)]
+    pub orphans: OrphanPolicy,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -32,10 +49,39 @@ pub struct Z2mConfig {
     pub servers: HashMap<String, Z2mServer>,
 }
 
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Z2mServerKind {
+    /// Connect to the z2m frontend websocket
+    #[default]
+    Websocket,
+    /// Connect to the mqtt broker used by z2m
+    Mqtt,
+}
+
+/// What to do with requests for a z2m server while it is disconnected
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OfflinePolicy {
+    /// Reject requests right away
+    #[default]
+    Fail,
+    /// Keep requests, and send them once reconnected
+    Queue,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Z2mServer {
     pub url: Url,
     pub group_prefix: Option<String>,
+    #[serde(default)]
+    pub kind: Z2mServerKind,
+    pub base_topic: Option<String>,
+    pub rate_limit: Option<u32>,
+    #[serde(default)]
+    pub sync_names: bool,
+    #[serde(default)]
+    pub offline_requests: OfflinePolicy,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
@@ -54,6 +100,26 @@ pub struct AppConfig {
 }
 
 impl Z2mServer {
+    pub const DEFAULT_BASE_TOPIC: &'static str = "zigbee2mqtt";
+    pub const DEFAULT_RATE_LIMIT: u32 = 10;
+
+    #[must_use]
+    pub fn get_base_topic(&self) -> &str {
+        self.base_topic
+            .as_deref()
+            .unwrap_or(Self::DEFAULT_BASE_TOPIC)
+    }
+
+    /// Minimum time between requests sent to z2m (`rate_limit` is in
+    /// requests per second, where 0 means unlimited)
+    #[must_use]
+    pub fn get_request_interval(&self) -> Duration {
+        match self.rate_limit.unwrap_or(Self::DEFAULT_RATE_LIMIT) {
+            0 => Duration::ZERO,
+            rate => Duration::from_secs(1) / rate,
+        }
+    }
+
     #[must_use]
     pub fn get_url(&self) -> Url {
         let mut url = self.url.clone();
@@ -109,6 +175,7 @@ pub fn parse(filename: &Utf8Path) -> Result<AppConfig, ConfigError> {
     let settings = Config::builder()
         .set_default("bifrost.state_file", "state.yaml")?
         .set_default("bifrost.cert_file", "cert.pem")?
+        .set_default("bifrost.wait_for_ack", false)?
         .set_default("bridge.http_port", 80)?
         .set_default("bridge.https_port", 443)?
         .add_source(config::File::with_name(filename.as_str()))
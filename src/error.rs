@@ -1,12 +1,11 @@
 use std::num::{ParseIntError, TryFromIntError};
-use std::sync::Arc;
 
 use camino::Utf8PathBuf;
 use thiserror::Error;
 use tokio::task::JoinError;
 use uuid::Uuid;
 
-use crate::backend::BackendRequest;
+use crate::backend::QueuedRequest;
 use crate::hue::{
     api::{RType, ResourceLink},
     event::EventBlock,
@@ -53,7 +52,7 @@ pub enum ApiError {
     SendErrorHue(#[from] tokio::sync::broadcast::error::SendError<EventBlock>),
 
     #[error(transparent)]
-    SendErrorZ2m(#[from] tokio::sync::broadcast::error::SendError<Arc<BackendRequest>>),
+    SendErrorZ2m(#[from] tokio::sync::mpsc::error::SendError<QueuedRequest>),
 
     #[error(transparent)]
     SetLoggerError(#[from] log::SetLoggerError),
@@ -70,6 +69,9 @@ pub enum ApiError {
     #[error(transparent)]
     TungsteniteError(#[from] tokio_tungstenite::tungstenite::Error),
 
+    #[error(transparent)]
+    MqttClientError(#[from] rumqttc::ClientError),
+
     #[error(transparent)]
     X509DerError(#[from] x509_cert::der::Error),
 
@@ -95,6 +97,12 @@ pub enum ApiError {
     #[error("Unexpected z2m message: {0:?}")]
     UnexpectedZ2mReply(tokio_tungstenite::tungstenite::Message),
 
+    #[error("Invalid mqtt url: {0}")]
+    InvalidMqttUrl(String),
+
+    #[error("Request failed in z2m: {0}")]
+    Z2mRequestFailed(String),
+
     /* hue api v1 errors */
     #[error("Cannot create resources of type: {0:?}")]
     V1CreateUnsupported(ApiResourceType),
@@ -122,6 +130,21 @@ pub enum ApiError {
     Full(RType),
 
     /* bifrost errors */
+    #[error("No backend owns resource {0}")]
+    NoBackendOwner(Uuid),
+
+    #[error("Cannot choose a backend for {0:?} without members, since several are configured")]
+    NoBackendForEmptyGroup(ResourceLink),
+
+    #[error("Backend request queue closed")]
+    BackendRequestsClosed,
+
+    #[error("Backend request failed: {0}")]
+    BackendRequestFailed(String),
+
+    #[error("Backend {0} is not connected")]
+    BackendOffline(String),
+
     #[error("Cannot parse state file: no version field found")]
     StateVersionNotFound,
 
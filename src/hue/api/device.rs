@@ -28,6 +28,13 @@ impl Device {
     pub fn light_service(&self) -> Option<&ResourceLink> {
         self.services.iter().find(|rl| rl.rtype == RType::Light)
     }
+
+    #[must_use]
+    pub fn zigbee_connectivity_service(&self) -> Option<&ResourceLink> {
+        self.services
+            .iter()
+            .find(|rl| rl.rtype == RType::ZigbeeConnectivity)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
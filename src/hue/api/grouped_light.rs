@@ -1,7 +1,11 @@
+use std::ops::AddAssign;
+
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::hue::api::{ColorTemperatureUpdate, ColorUpdate, DimmingUpdate, On, ResourceLink, Stub};
+use crate::hue::api::{
+    ColorTemperatureUpdate, ColorUpdate, DimmingUpdate, Light, LightUpdate, On, ResourceLink, Stub,
+};
 use crate::model::types::XY;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -9,9 +13,9 @@ pub struct GroupedLight {
     pub alert: Value,
     pub dimming: Option<DimmingUpdate>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub color: Option<Stub>,
+    pub color: Option<GroupedLightColor>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub color_temperature: Option<Stub>,
+    pub color_temperature: Option<GroupedLightColorTemperature>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub color_temperature_delta: Option<Stub>,
     #[serde(default)]
@@ -29,8 +33,8 @@ impl GroupedLight {
         Self {
             alert: Value::Null,
             dimming: None,
-            color: Some(Stub {}),
-            color_temperature: Some(Stub {}),
+            color: Some(GroupedLightColor { xy: None }),
+            color_temperature: Some(GroupedLightColorTemperature { mirek: None }),
             color_temperature_delta: Some(Stub {}),
             dimming_delta: Stub {},
             dynamics: Stub {},
@@ -44,6 +48,86 @@ impl GroupedLight {
     pub fn as_brightness_opt(&self) -> Option<f64> {
         self.dimming.as_ref().map(|br| br.brightness)
     }
+
+    #[must_use]
+    pub fn as_mirek_opt(&self) -> Option<u16> {
+        self.color_temperature.and_then(|ct| ct.mirek)
+    }
+
+    #[must_use]
+    pub fn as_color_opt(&self) -> Option<XY> {
+        self.color.and_then(|col| col.xy)
+    }
+
+    /// Compute the group state from its member lights, the way a Hue bridge
+    /// does: the group is on if any light is on, and brightness, color and
+    /// color temperature are averaged over the lights that are on (or over
+    /// all lights, if none are).
+    #[must_use]
+    pub fn aggregate(&self, lights: &[&Light]) -> Self {
+        let on: Vec<&Light> = lights.iter().copied().filter(|l| l.on.on).collect();
+        let active = if on.is_empty() { lights } else { &on };
+
+        let dimming = average(
+            active
+                .iter()
+                .filter_map(|l| l.dimming)
+                .map(|d| d.brightness),
+        )
+        .map(|brightness| DimmingUpdate { brightness });
+
+        let xy = average_xy(active.iter().filter_map(|l| l.as_color_opt()));
+
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let mirek = average(
+            active
+                .iter()
+                .filter_map(|l| l.as_mirek_opt().map(f64::from)),
+        )
+        .map(|m| m.round() as u16);
+
+        Self {
+            on: Some(On::new(!on.is_empty())),
+            dimming,
+            color: Some(GroupedLightColor { xy }),
+            color_temperature: Some(GroupedLightColorTemperature { mirek }),
+            ..self.clone()
+        }
+    }
+
+    /// Returns true if the aggregated light state differs from `other`
+    #[must_use]
+    pub fn state_differs(&self, other: &Self) -> bool {
+        self.on != other.on
+            || self.as_brightness_opt() != other.as_brightness_opt()
+            || self.as_color_opt() != other.as_color_opt()
+            || self.as_mirek_opt() != other.as_mirek_opt()
+    }
+}
+
+fn average(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0u32), |(sum, count), v| (sum + v, count + 1));
+    (count > 0).then(|| sum / f64::from(count))
+}
+
+fn average_xy(values: impl Iterator<Item = XY>) -> Option<XY> {
+    let points: Vec<XY> = values.collect();
+    Some(XY::new(
+        average(points.iter().map(|p| p.x))?,
+        average(points.iter().map(|p| p.y))?,
+    ))
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct GroupedLightColor {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub xy: Option<XY>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct GroupedLightColorTemperature {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mirek: Option<u16>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
@@ -101,3 +185,72 @@ impl GroupedLightUpdate {
         }
     }
 }
+
+/// A grouped light update applies the same change to every member light
+impl From<&GroupedLightUpdate> for LightUpdate {
+    fn from(upd: &GroupedLightUpdate) -> Self {
+        Self {
+            on: upd.on,
+            dimming: upd.dimming,
+            color: upd.color,
+            color_temperature: upd.color_temperature,
+            ..Self::default()
+        }
+    }
+}
+
+/// Merge a newer update into this one, following the same rules as for
+/// light updates
+impl AddAssign<Self> for GroupedLightUpdate {
+    fn add_assign(&mut self, upd: Self) {
+        self.on = upd.on.or(self.on);
+        self.dimming = upd.dimming.or(self.dimming);
+
+        if upd.color.is_some() {
+            self.color = upd.color;
+            self.color_temperature = None;
+        }
+
+        if upd.color_temperature.is_some() {
+            self.color_temperature = upd.color_temperature;
+            self.color = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hue::api::{
+        DeviceArchetype, Dimming, GroupedLight, Light, LightMetadata, On, RType,
+    };
+
+    fn light(on: bool, brightness: f64) -> Light {
+        let mut light = Light::new(
+            RType::Device.deterministic(brightness.to_string()),
+            LightMetadata::new(DeviceArchetype::SultanBulb, "light"),
+        );
+        light.on = On::new(on);
+        light.dimming = Some(Dimming {
+            brightness,
+            min_dim_level: None,
+        });
+        light
+    }
+
+    #[test]
+    fn aggregate_any_on() {
+        let glight = GroupedLight::new(RType::Room.deterministic("room"));
+
+        let (a, b, c) = (light(true, 80.0), light(true, 40.0), light(false, 10.0));
+        let agg = glight.aggregate(&[&a, &b, &c]);
+        assert_eq!(agg.on, Some(On::new(true)));
+        assert_eq!(agg.as_brightness_opt(), Some(60.0));
+        assert!(agg.state_differs(&glight));
+        assert!(!agg.aggregate(&[&a, &b, &c]).state_differs(&agg));
+
+        let (a, b) = (light(false, 20.0), light(false, 40.0));
+        let agg = glight.aggregate(&[&a, &b]);
+        assert_eq!(agg.on, Some(On::new(false)));
+        assert_eq!(agg.as_brightness_opt(), Some(30.0));
+    }
+}
@@ -193,7 +193,9 @@ impl AddAssign<LightUpdate> for Light {
         }
 
         if let Some(ct) = &mut self.color_temperature {
-            ct.mirek = upd.color_temperature.map(|c| c.mirek);
+            if let Some(c) = upd.color_temperature {
+                ct.mirek = Some(c.mirek);
+            }
         }
 
         if let Some(col) = upd.color {
@@ -559,6 +561,17 @@ impl LightUpdate {
         Self::default()
     }
 
+    #[must_use]
+    pub fn with_metadata(self, md: &LightMetadata) -> Self {
+        Self {
+            metadata: Some(MetadataUpdate {
+                name: Some(md.name.clone()),
+                archetype: Some(md.archetype.clone()),
+            }),
+            ..self
+        }
+    }
+
     #[must_use]
     pub fn with_brightness(self, dim: Option<impl Into<f64>>) -> Self {
         Self {
@@ -603,6 +616,34 @@ impl LightUpdate {
     }
 }
 
+/// Merge a newer update into this one. Fields set in the newer update take
+/// precedence, and color and color temperature replace each other.
+impl AddAssign<Self> for LightUpdate {
+    fn add_assign(&mut self, upd: Self) {
+        if let Some(md) = upd.metadata {
+            let old = self.metadata.get_or_insert_with(MetadataUpdate::default);
+            old.name = md.name.or_else(|| old.name.take());
+            old.archetype = md.archetype.or_else(|| old.archetype.take());
+        }
+
+        self.on = upd.on.or(self.on);
+        self.dimming = upd.dimming.or(self.dimming);
+
+        if upd.color.is_some() {
+            self.color = upd.color;
+            self.color_temperature = None;
+        }
+
+        if upd.color_temperature.is_some() {
+            self.color_temperature = upd.color_temperature;
+            self.color = None;
+        }
+
+        self.gradient = upd.gradient.or_else(|| self.gradient.take());
+        self.effects_v2 = upd.effects_v2.or_else(|| self.effects_v2.take());
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
 pub struct DimmingUpdate {
     pub brightness: f64,
@@ -9,7 +9,9 @@ mod update;
 
 pub use device::{Device, DeviceArchetype, DeviceProductData, DeviceUpdate, Identify};
 
-pub use grouped_light::{GroupedLight, GroupedLightUpdate};
+pub use grouped_light::{
+    GroupedLight, GroupedLightColor, GroupedLightColorTemperature, GroupedLightUpdate,
+};
 pub use light::{
     ColorGamut, ColorTemperature, ColorTemperatureUpdate, ColorUpdate, Delta, Dimming,
     DimmingUpdate, GamutType, Light, LightAlert, LightColor, LightDynamics, LightDynamicsStatus,
@@ -21,19 +23,26 @@ pub use light::{
     LightUpdate, MirekSchema, On,
 };
 pub use resource::{RType, ResourceLink, ResourceRecord};
-pub use room::{Room, RoomArchetype, RoomMetadata, RoomMetadataUpdate, RoomUpdate};
+pub use room::{
+    Room, RoomArchetype, RoomMetadata, RoomMetadataUpdate, RoomUpdate, Zone, ZoneUpdate,
+};
 pub use scene::{
     Scene, SceneAction, SceneActionElement, SceneActive, SceneMetadata, SceneRecall, SceneStatus,
     SceneStatusUpdate, SceneUpdate,
 };
 pub use stubs::{
-    BehaviorInstance, BehaviorInstanceMetadata, BehaviorScript, Bridge, BridgeHome, Button,
-    ButtonData, ButtonMetadata, ButtonReport, DevicePower, DeviceSoftwareUpdate, DollarRef,
-    Entertainment, EntertainmentConfiguration, EntertainmentSegment, EntertainmentSegments,
-    GeofenceClient, Geolocation, GroupedLightLevel, GroupedMotion, Homekit, LightLevel, Matter,
-    Metadata, MetadataUpdate, Motion, PrivateGroup, PublicImage, RelativeRotary, SmartScene,
-    Taurus, Temperature, TimeZone, ZigbeeConnectivity, ZigbeeConnectivityStatus,
-    ZigbeeDeviceDiscovery, Zone,
+    BatteryState, BehaviorInstance, BehaviorInstanceMetadata, BehaviorScript, Bridge, BridgeHome,
+    Button, ButtonData, ButtonEvent, ButtonMetadata, ButtonReport, ButtonUpdate, DevicePower,
+    DevicePowerUpdate, DeviceSoftwareUpdate, DollarRef, Entertainment, EntertainmentConfiguration,
+    EntertainmentSegment, EntertainmentSegments, GeofenceClient, Geolocation, GroupedLightLevel,
+    GroupedMotion, Homekit, LightLevel, LightLevelData, LightLevelReport, LightLevelUpdate, Matter,
+    Metadata, MetadataUpdate, Motion, MotionData, MotionReport, MotionUpdate, PowerState,
+    PrivateGroup, PublicImage, RelativeRotary, RelativeRotaryData, RelativeRotaryUpdate,
+    RotaryAction, RotaryDirection, RotaryEvent, RotaryReport, Rotation, SmartScene, Taurus,
+    Temperature, TemperatureData, TemperatureReport, TemperatureUpdate, TimeZone,
+    ZigbeeConnectivity, ZigbeeConnectivityStatus, ZigbeeConnectivityUpdate, ZigbeeDeviceDiscovery,
+    ZigbeeDeviceDiscoveryAction, ZigbeeDeviceDiscoveryActionType, ZigbeeDeviceDiscoveryStatus,
+    ZigbeeDeviceDiscoveryUpdate,
 };
 pub use update::{Update, UpdateRecord};
 
@@ -41,6 +50,7 @@ use std::fmt::Debug;
 
 use serde::{Deserialize, Serialize};
 use serde_json::{from_value, json, Value};
+use uuid::Uuid;
 
 use crate::error::{ApiError, ApiResult};
 use crate::hue::legacy_api::ApiLightStateUpdate;
@@ -163,6 +173,72 @@ impl Resource {
         };
         Ok(res)
     }
+
+    /// The resource this one belongs to: the device (or group) owning a
+    /// service, or the room or zone of a scene
+    #[must_use]
+    pub const fn owner(&self) -> Option<&ResourceLink> {
+        match self {
+            Self::Bridge(Bridge { owner, .. })
+            | Self::Button(Button { owner, .. })
+            | Self::DevicePower(DevicePower { owner, .. })
+            | Self::DeviceSoftwareUpdate(DeviceSoftwareUpdate { owner, .. })
+            | Self::Entertainment(Entertainment { owner, .. })
+            | Self::GroupedLight(GroupedLight { owner, .. })
+            | Self::GroupedLightLevel(GroupedLightLevel { owner, .. })
+            | Self::GroupedMotion(GroupedMotion { owner, .. })
+            | Self::Light(Light { owner, .. })
+            | Self::LightLevel(LightLevel { owner, .. })
+            | Self::Motion(Motion { owner, .. })
+            | Self::RelativeRotary(RelativeRotary { owner, .. })
+            | Self::Temperature(Temperature { owner, .. })
+            | Self::ZigbeeConnectivity(ZigbeeConnectivity { owner, .. })
+            | Self::ZigbeeDeviceDiscovery(ZigbeeDeviceDiscovery { owner, .. })
+            | Self::Scene(Scene { group: owner, .. })
+            | Self::SmartScene(SmartScene { group: owner, .. }) => Some(owner),
+            _ => None,
+        }
+    }
+
+    /// All links from this resource to other resources: the owner, the
+    /// children and services of devices, rooms, zones and the bridge home,
+    /// and the targets of scene actions
+    #[must_use]
+    pub fn links(&self) -> Vec<&ResourceLink> {
+        let mut links: Vec<_> = self.owner().into_iter().collect();
+        match self {
+            Self::Device(dev) => links.extend(&dev.services),
+            Self::Room(room) => links.extend(room.children.iter().chain(&room.services)),
+            Self::Zone(zone) => links.extend(zone.children.iter().chain(&zone.services)),
+            Self::BridgeHome(home) => links.extend(home.children.iter().chain(&home.services)),
+            Self::Scene(scene) => links.extend(scene.actions.iter().map(|act| &act.target)),
+            _ => {}
+        }
+        links
+    }
+
+    /// Remove links to `id` from the children and services of this resource,
+    /// and from its scene actions. Returns true if anything was removed.
+    pub fn remove_links_to(&mut self, id: &Uuid) -> bool {
+        fn strip(links: &mut Vec<ResourceLink>, id: &Uuid) -> bool {
+            let len = links.len();
+            links.retain(|link| link.rid != *id);
+            links.len() != len
+        }
+
+        match self {
+            Self::Device(dev) => strip(&mut dev.services, id),
+            Self::Room(room) => strip(&mut room.children, id) | strip(&mut room.services, id),
+            Self::Zone(zone) => strip(&mut zone.children, id) | strip(&mut zone.services, id),
+            Self::BridgeHome(home) => strip(&mut home.children, id) | strip(&mut home.services, id),
+            Self::Scene(scene) => {
+                let len = scene.actions.len();
+                scene.actions.retain(|act| act.target.rid != *id);
+                scene.actions.len() != len
+            }
+            _ => false,
+        }
+    }
 }
 
 #[macro_export]
@@ -218,6 +294,7 @@ resource_conversion_impl!(Bridge);
 resource_conversion_impl!(BridgeHome);
 resource_conversion_impl!(Button);
 resource_conversion_impl!(Device);
+resource_conversion_impl!(DevicePower);
 resource_conversion_impl!(Entertainment);
 resource_conversion_impl!(EntertainmentConfiguration);
 resource_conversion_impl!(GeofenceClient);
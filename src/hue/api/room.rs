@@ -24,6 +24,18 @@ pub struct Room {
     pub services: Vec<ResourceLink>,
 }
 
+/// A group of lights, which (unlike rooms) may overlap other zones and rooms
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Zone {
+    pub children: Vec<ResourceLink>,
+    pub metadata: RoomMetadata,
+    #[serde(default)]
+    pub services: Vec<ResourceLink>,
+}
+
+/// Zones are updated like rooms, except the children are lights
+pub type ZoneUpdate = RoomUpdate;
+
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct RoomUpdate {
     pub children: Option<Vec<ResourceLink>>,
@@ -39,6 +51,15 @@ impl Room {
     }
 }
 
+impl Zone {
+    #[must_use]
+    pub fn grouped_light_service(&self) -> Option<&ResourceLink> {
+        self.services
+            .iter()
+            .find(|rl| rl.rtype == RType::GroupedLight)
+    }
+}
+
 impl RoomUpdate {
     #[must_use]
     pub fn new() -> Self {
@@ -55,6 +76,11 @@ impl RoomUpdate {
             ..self
         }
     }
+
+    #[must_use]
+    pub fn with_children(self, children: Option<Vec<ResourceLink>>) -> Self {
+        Self { children, ..self }
+    }
 }
 
 #[derive(Copy, Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use uuid::Uuid;
 
-use crate::hue::api::{DeviceArchetype, ResourceLink, SceneMetadata};
+use crate::hue::api::{DeviceArchetype, RType, ResourceLink, SceneMetadata};
 use crate::hue::{best_guess_timezone, date_format};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -19,6 +19,15 @@ pub struct BridgeHome {
     pub services: Vec<ResourceLink>,
 }
 
+impl BridgeHome {
+    #[must_use]
+    pub fn grouped_light_service(&self) -> Option<&ResourceLink> {
+        self.services
+            .iter()
+            .find(|rl| rl.rtype == RType::GroupedLight)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Button {
     pub owner: ResourceLink,
@@ -26,6 +35,32 @@ pub struct Button {
     pub button: ButtonData,
 }
 
+impl Button {
+    pub const REPEAT_INTERVAL: u32 = 800;
+
+    #[must_use]
+    pub fn new(owner: ResourceLink, control_id: u32) -> Self {
+        Self {
+            owner,
+            metadata: ButtonMetadata { control_id },
+            button: ButtonData {
+                button_report: None,
+                last_event: None,
+                repeat_interval: Some(Self::REPEAT_INTERVAL),
+                event_values: Some(ButtonEvent::ALL.into()),
+            },
+        }
+    }
+
+    pub fn report_event(&mut self, event: ButtonEvent) {
+        self.button.last_event = Some(event);
+        self.button.button_report = Some(ButtonReport {
+            updated: Utc::now(),
+            event,
+        });
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ButtonMetadata {
     pub control_id: u32,
@@ -36,18 +71,56 @@ pub struct ButtonData {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub button_report: Option<ButtonReport>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub last_event: Option<Value>,
+    pub last_event: Option<ButtonEvent>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub repeat_interval: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub event_values: Option<Value>,
+    pub event_values: Option<Vec<ButtonEvent>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ButtonReport {
     #[serde(with = "date_format::utc_ms")]
     pub updated: DateTime<Utc>,
-    pub event: String,
+    pub event: ButtonEvent,
+}
+
+#[derive(Copy, Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ButtonEvent {
+    InitialPress,
+    Repeat,
+    ShortRelease,
+    LongRelease,
+    DoubleShortRelease,
+    LongPress,
+}
+
+impl ButtonEvent {
+    pub const ALL: [Self; 4] = [
+        Self::InitialPress,
+        Self::Repeat,
+        Self::ShortRelease,
+        Self::LongRelease,
+    ];
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ButtonUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub button: Option<ButtonData>,
+}
+
+impl ButtonUpdate {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn with_button(self, button: Option<ButtonData>) -> Self {
+        Self { button }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -59,7 +132,91 @@ pub struct DollarRef {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DevicePower {
     pub owner: ResourceLink,
-    pub power_state: Value,
+    pub power_state: PowerState,
+}
+
+impl DevicePower {
+    #[must_use]
+    pub const fn new(owner: ResourceLink) -> Self {
+        Self {
+            owner,
+            power_state: PowerState {
+                battery_state: None,
+                battery_level: None,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct PowerState {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub battery_state: Option<BatteryState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub battery_level: Option<u8>,
+}
+
+impl PowerState {
+    /// Build power state from battery percentage and/or low battery warning
+    #[must_use]
+    pub const fn from_battery(level: Option<u8>, low: Option<bool>) -> Self {
+        let battery_state = match (level, low) {
+            (Some(level), _) => Some(BatteryState::from_level(level)),
+            (None, Some(true)) => Some(BatteryState::Low),
+            (None, Some(false)) => Some(BatteryState::Normal),
+            (None, None) => None,
+        };
+
+        Self {
+            battery_state,
+            battery_level: level,
+        }
+    }
+}
+
+#[derive(Copy, Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatteryState {
+    Normal,
+    Low,
+    Critical,
+}
+
+impl BatteryState {
+    /// Battery level (in percent) below which the battery is low
+    pub const LOW_LEVEL: u8 = 25;
+
+    /// Battery level (in percent) below which the battery is critical
+    pub const CRITICAL_LEVEL: u8 = 5;
+
+    #[must_use]
+    pub const fn from_level(level: u8) -> Self {
+        if level < Self::CRITICAL_LEVEL {
+            Self::Critical
+        } else if level < Self::LOW_LEVEL {
+            Self::Low
+        } else {
+            Self::Normal
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DevicePowerUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub power_state: Option<PowerState>,
+}
+
+impl DevicePowerUpdate {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub const fn with_power_state(self, power_state: Option<PowerState>) -> Self {
+        Self { power_state }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -237,10 +394,94 @@ impl Default for Homekit {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LightLevel {
     pub enabled: bool,
-    pub light: Value,
+    pub light: LightLevelData,
     pub owner: ResourceLink,
 }
 
+impl LightLevel {
+    #[must_use]
+    pub fn new(owner: ResourceLink) -> Self {
+        Self {
+            enabled: true,
+            light: LightLevelData::default(),
+            owner,
+        }
+    }
+
+    /// Convert illuminance (in lux) to the logarithmic scale used by Hue:
+    ///
+    ///   `light_level = 10000 * log10(lux) + 1`
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn light_level_from_lux(lux: f64) -> u32 {
+        if lux < 1.0 {
+            return 0;
+        }
+        (10000.0f64.mul_add(lux.log10(), 1.0)).round() as u32
+    }
+
+    #[must_use]
+    pub fn as_light_level_opt(&self) -> Option<u32> {
+        self.light
+            .light_level_report
+            .as_ref()
+            .map(|rep| rep.light_level)
+    }
+
+    pub fn report_light_level(&mut self, light_level: u32) {
+        self.light = LightLevelData {
+            light_level: Some(light_level),
+            light_level_valid: true,
+            light_level_report: Some(LightLevelReport {
+                changed: Utc::now(),
+                light_level,
+            }),
+        };
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct LightLevelData {
+    /* deprecated in favor of .light_level_report, but still sent by real bridges */
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub light_level: Option<u32>,
+    pub light_level_valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub light_level_report: Option<LightLevelReport>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LightLevelReport {
+    #[serde(with = "date_format::utc_ms")]
+    pub changed: DateTime<Utc>,
+    pub light_level: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct LightLevelUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub light: Option<LightLevelData>,
+}
+
+impl LightLevelUpdate {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub const fn with_enabled(self, enabled: Option<bool>) -> Self {
+        Self { enabled, ..self }
+    }
+
+    #[must_use]
+    pub const fn with_light(self, light: Option<LightLevelData>) -> Self {
+        Self { light, ..self }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Matter {
     pub has_qr_code: bool,
@@ -251,11 +492,81 @@ pub struct Matter {
 pub struct Motion {
     pub enabled: bool,
     pub owner: ResourceLink,
-    pub motion: Value,
-    #[serde(default)]
+    pub motion: MotionData,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
     pub sensitivity: Value,
 }
 
+impl Motion {
+    #[must_use]
+    pub fn new(owner: ResourceLink) -> Self {
+        Self {
+            enabled: true,
+            owner,
+            motion: MotionData::default(),
+            sensitivity: Value::Null,
+        }
+    }
+
+    #[must_use]
+    pub fn as_motion_opt(&self) -> Option<bool> {
+        self.motion.motion_report.as_ref().map(|rep| rep.motion)
+    }
+
+    pub fn report_motion(&mut self, motion: bool) {
+        self.motion = MotionData {
+            motion: Some(motion),
+            motion_valid: true,
+            motion_report: Some(MotionReport {
+                changed: Utc::now(),
+                motion,
+            }),
+        };
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MotionData {
+    /* deprecated in favor of .motion_report, but still sent by real bridges */
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub motion: Option<bool>,
+    pub motion_valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub motion_report: Option<MotionReport>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MotionReport {
+    #[serde(with = "date_format::utc_ms")]
+    pub changed: DateTime<Utc>,
+    pub motion: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MotionUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub motion: Option<MotionData>,
+}
+
+impl MotionUpdate {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub const fn with_enabled(self, enabled: Option<bool>) -> Self {
+        Self { enabled, ..self }
+    }
+
+    #[must_use]
+    pub const fn with_motion(self, motion: Option<MotionData>) -> Self {
+        Self { motion, ..self }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PrivateGroup {}
 
@@ -265,10 +576,110 @@ pub struct PublicImage {}
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct RelativeRotary {
     pub owner: ResourceLink,
+    pub relative_rotary: RelativeRotaryData,
+}
+
+impl RelativeRotary {
+    /// Turns closer together than this (in milliseconds) are reported as
+    /// continuations of the same rotation
+    pub const REPEAT_INTERVAL: i64 = 1000;
+
+    #[must_use]
+    pub const fn new(owner: ResourceLink) -> Self {
+        Self {
+            owner,
+            relative_rotary: RelativeRotaryData {
+                last_event: None,
+                rotary_report: None,
+            },
+        }
+    }
+
+    pub fn report_rotation(&mut self, rotation: Rotation) {
+        let now = Utc::now();
+
+        let action = match &self.relative_rotary.rotary_report {
+            Some(report)
+                if report.rotation.direction == rotation.direction
+                    && (now - report.updated).num_milliseconds() < Self::REPEAT_INTERVAL =>
+            {
+                RotaryAction::Repeat
+            }
+            _ => RotaryAction::Start,
+        };
+
+        self.relative_rotary.last_event = Some(RotaryEvent {
+            action,
+            rotation: rotation.clone(),
+        });
+        self.relative_rotary.rotary_report = Some(RotaryReport {
+            updated: now,
+            action,
+            rotation,
+        });
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RelativeRotaryData {
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub relative_rotary: Option<Value>,
+    pub last_event: Option<RotaryEvent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rotary_report: Option<RotaryReport>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RotaryEvent {
+    pub action: RotaryAction,
+    pub rotation: Rotation,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RotaryReport {
+    #[serde(with = "date_format::utc_ms")]
+    pub updated: DateTime<Utc>,
+    pub action: RotaryAction,
+    pub rotation: Rotation,
+}
+
+#[derive(Copy, Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RotaryAction {
+    Start,
+    Repeat,
+}
+
+#[derive(Copy, Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RotaryDirection {
+    ClockWise,
+    CounterClockWise,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Rotation {
+    pub direction: RotaryDirection,
+    pub steps: u32,
+    /// Duration of the rotation, in milliseconds
+    pub duration: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RelativeRotaryUpdate {
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub rotary_report: Option<Value>,
+    pub relative_rotary: Option<RelativeRotaryData>,
+}
+
+impl RelativeRotaryUpdate {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub const fn with_relative_rotary(self, relative_rotary: Option<RelativeRotaryData>) -> Self {
+        Self { relative_rotary }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -288,13 +699,23 @@ pub struct SmartScene {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Taurus {}
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Copy, Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum ZigbeeConnectivityStatus {
     Connected,
     ConnectivityIssue,
 }
 
+impl From<bool> for ZigbeeConnectivityStatus {
+    fn from(connected: bool) -> Self {
+        if connected {
+            Self::Connected
+        } else {
+            Self::ConnectivityIssue
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ZigbeeConnectivity {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -306,28 +727,173 @@ pub struct ZigbeeConnectivity {
     pub status: ZigbeeConnectivityStatus,
 }
 
+impl ZigbeeConnectivity {
+    #[must_use]
+    pub const fn new(owner: ResourceLink, mac_address: String) -> Self {
+        Self {
+            channel: None,
+            extended_pan_id: None,
+            mac_address,
+            owner,
+            status: ZigbeeConnectivityStatus::Connected,
+        }
+    }
+
+    #[must_use]
+    pub fn is_connected(&self) -> bool {
+        self.status == ZigbeeConnectivityStatus::Connected
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ZigbeeConnectivityUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<ZigbeeConnectivityStatus>,
+}
+
+impl ZigbeeConnectivityUpdate {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub const fn with_status(self, status: Option<ZigbeeConnectivityStatus>) -> Self {
+        Self { status }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ZigbeeDeviceDiscovery {
     pub owner: ResourceLink,
-    pub status: String,
+    pub status: ZigbeeDeviceDiscoveryStatus,
     #[serde(default)]
     #[serde(skip_serializing_if = "Value::is_null")]
     pub action: Value,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ZigbeeDeviceDiscoveryStatus {
+    /// Searching for new devices (the join window is open)
+    Active,
+    Ready,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ZigbeeDeviceDiscoveryActionType {
+    Search,
+    SearchAllowDefaultLinkKey,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Zone {
-    pub metadata: Metadata,
-    pub children: Vec<ResourceLink>,
-    #[serde(default)]
-    pub services: Vec<ResourceLink>,
+pub struct ZigbeeDeviceDiscoveryAction {
+    pub action_type: ZigbeeDeviceDiscoveryActionType,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub search_codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ZigbeeDeviceDiscoveryUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<ZigbeeDeviceDiscoveryAction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<ZigbeeDeviceDiscoveryStatus>,
+}
+
+impl ZigbeeDeviceDiscoveryUpdate {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn with_status(self, status: Option<ZigbeeDeviceDiscoveryStatus>) -> Self {
+        Self { status, ..self }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Temperature {
     pub enabled: bool,
     pub owner: ResourceLink,
-    pub temperature: Value,
+    pub temperature: TemperatureData,
+}
+
+impl Temperature {
+    #[must_use]
+    pub fn new(owner: ResourceLink) -> Self {
+        Self {
+            enabled: true,
+            owner,
+            temperature: TemperatureData::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn as_temperature_opt(&self) -> Option<f64> {
+        self.temperature
+            .temperature_report
+            .as_ref()
+            .map(|rep| rep.temperature)
+    }
+
+    pub fn report_temperature(&mut self, temperature: f64) {
+        self.temperature = TemperatureData {
+            temperature: Some(temperature),
+            temperature_valid: true,
+            temperature_report: Some(TemperatureReport {
+                changed: Utc::now(),
+                temperature,
+            }),
+        };
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TemperatureData {
+    /* deprecated in favor of .temperature_report, but still sent by real bridges */
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    pub temperature_valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature_report: Option<TemperatureReport>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TemperatureReport {
+    #[serde(with = "date_format::utc_ms")]
+    pub changed: DateTime<Utc>,
+    pub temperature: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TemperatureUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<TemperatureData>,
+}
+
+impl TemperatureUpdate {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub const fn with_enabled(self, enabled: Option<bool>) -> Self {
+        Self { enabled, ..self }
+    }
+
+    #[must_use]
+    pub const fn with_temperature(self, temperature: Option<TemperatureData>) -> Self {
+        Self {
+            temperature,
+            ..self
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -365,3 +931,35 @@ pub struct MetadataUpdate {
     pub name: Option<String>,
     pub archetype: Option<DeviceArchetype>,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::hue::api::{BatteryState, LightLevel, PowerState};
+
+    #[test]
+    fn light_level_from_lux() {
+        assert_eq!(LightLevel::light_level_from_lux(0.0), 0);
+        assert_eq!(LightLevel::light_level_from_lux(0.5), 0);
+        assert_eq!(LightLevel::light_level_from_lux(1.0), 1);
+        assert_eq!(LightLevel::light_level_from_lux(10.0), 10001);
+        assert_eq!(LightLevel::light_level_from_lux(100.0), 20001);
+        assert_eq!(LightLevel::light_level_from_lux(1000.0), 30001);
+    }
+
+    #[test]
+    fn power_state_from_battery() {
+        let state = PowerState::from_battery(Some(80), None);
+        assert_eq!(state.battery_state, Some(BatteryState::Normal));
+        assert_eq!(state.battery_level, Some(80));
+
+        let state = PowerState::from_battery(Some(10), Some(false));
+        assert_eq!(state.battery_state, Some(BatteryState::Low));
+
+        let state = PowerState::from_battery(Some(2), None);
+        assert_eq!(state.battery_state, Some(BatteryState::Critical));
+
+        let state = PowerState::from_battery(None, Some(true));
+        assert_eq!(state.battery_state, Some(BatteryState::Low));
+        assert_eq!(state.battery_level, None);
+    }
+}
@@ -2,7 +2,9 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::hue::api::{
-    DeviceUpdate, GroupedLightUpdate, LightUpdate, RType, RoomUpdate, SceneUpdate,
+    ButtonUpdate, DevicePowerUpdate, DeviceUpdate, GroupedLightUpdate, LightLevelUpdate,
+    LightUpdate, MotionUpdate, RType, RelativeRotaryUpdate, RoomUpdate, SceneUpdate,
+    TemperatureUpdate, ZigbeeConnectivityUpdate, ZigbeeDeviceDiscoveryUpdate, ZoneUpdate,
 };
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -12,21 +14,27 @@ pub enum Update {
     /* BehaviorInstance(BehaviorInstanceUpdate), */
     /* Bridge(BridgeUpdate), */
     /* BridgeHome(BridgeHomeUpdate), */
+    Button(ButtonUpdate),
     Device(DeviceUpdate),
+    DevicePower(DevicePowerUpdate),
     /* Entertainment(EntertainmentUpdate), */
     /* GeofenceClient(GeofenceClientUpdate), */
     /* Geolocation(GeolocationUpdate), */
     GroupedLight(GroupedLightUpdate),
     /* Homekit(HomekitUpdate), */
     Light(LightUpdate),
+    LightLevel(LightLevelUpdate),
     /* Matter(MatterUpdate), */
+    Motion(MotionUpdate),
     /* PublicImage(PublicImageUpdate), */
+    RelativeRotary(RelativeRotaryUpdate),
     Room(RoomUpdate),
     Scene(SceneUpdate),
     /* SmartScene(SmartSceneUpdate), */
-    /* ZigbeeConnectivity(ZigbeeConnectivityUpdate), */
-    /* ZigbeeDeviceDiscovery(ZigbeeDeviceDiscoveryUpdate), */
-    /* Zone(ZoneUpdate), */
+    Temperature(TemperatureUpdate),
+    ZigbeeConnectivity(ZigbeeConnectivityUpdate),
+    ZigbeeDeviceDiscovery(ZigbeeDeviceDiscoveryUpdate),
+    Zone(ZoneUpdate),
 }
 
 impl Update {
@@ -34,20 +42,37 @@ impl Update {
     pub const fn rtype(&self) -> RType {
         match self {
             Self::GroupedLight(_) => RType::GroupedLight,
+            Self::Button(_) => RType::Button,
             Self::Device(_) => RType::Device,
+            Self::DevicePower(_) => RType::DevicePower,
             Self::Light(_) => RType::Light,
+            Self::LightLevel(_) => RType::LightLevel,
+            Self::Motion(_) => RType::Motion,
+            Self::RelativeRotary(_) => RType::RelativeRotary,
             Self::Room(_) => RType::Room,
             Self::Scene(_) => RType::Scene,
+            Self::Temperature(_) => RType::Temperature,
+            Self::ZigbeeConnectivity(_) => RType::ZigbeeConnectivity,
+            Self::ZigbeeDeviceDiscovery(_) => RType::ZigbeeDeviceDiscovery,
+            Self::Zone(_) => RType::Zone,
         }
     }
 
     #[must_use]
     pub fn id_v1_scope(&self, id: u32, uuid: &Uuid) -> Option<String> {
         match self {
-            Self::Room(_) | Self::GroupedLight(_) => Some(format!("/groups/{id}")),
+            Self::Room(_) | Self::Zone(_) | Self::GroupedLight(_) => Some(format!("/groups/{id}")),
             Self::Device(_) => Some(format!("/device/{id}")),
             Self::Light(_) => Some(format!("/lights/{id}")),
             Self::Scene(_) => Some(format!("/scenes/{uuid}")),
+            Self::Button(_)
+            | Self::DevicePower(_)
+            | Self::Motion(_)
+            | Self::LightLevel(_)
+            | Self::RelativeRotary(_)
+            | Self::Temperature(_)
+            | Self::ZigbeeConnectivity(_)
+            | Self::ZigbeeDeviceDiscovery(_) => None,
         }
     }
 }
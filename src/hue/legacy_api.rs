@@ -21,6 +21,20 @@ pub struct HueError {
     description: String,
 }
 
+impl HueError {
+    /// Hue api v1 error type "Internal error"
+    pub const INTERNAL_ERROR: u32 = 901;
+
+    #[must_use]
+    pub fn internal_error(address: &str, description: String) -> Self {
+        Self {
+            typ: Self::INTERNAL_ERROR,
+            address: address.to_string(),
+            description,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum HueResult<T> {
@@ -321,15 +335,44 @@ pub struct ApiGroup {
 }
 
 impl ApiGroup {
-    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
     #[must_use]
     pub fn from_lights_and_room(
         glight: &api::GroupedLight,
         lights: Vec<String>,
         room: api::Room,
+    ) -> Self {
+        Self::from_grouped_light(glight, lights, room.metadata.name, ApiGroupType::Room)
+    }
+
+    #[must_use]
+    pub fn from_lights_and_zone(
+        glight: &api::GroupedLight,
+        lights: Vec<String>,
+        zone: api::Zone,
+    ) -> Self {
+        Self::from_grouped_light(glight, lights, zone.metadata.name, ApiGroupType::Zone)
+    }
+
+    /// The v1 "group 0", which contains every light
+    #[must_use]
+    pub fn from_lights_and_bridge_home(glight: &api::GroupedLight, lights: Vec<String>) -> Self {
+        Self::from_grouped_light(
+            glight,
+            lights,
+            "Group 0".to_string(),
+            ApiGroupType::LightGroup,
+        )
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn from_grouped_light(
+        glight: &api::GroupedLight,
+        lights: Vec<String>,
+        name: String,
+        group_type: ApiGroupType,
     ) -> Self {
         Self {
-            name: room.metadata.name,
+            name,
             lights,
             action: ApiGroupAction {
                 on: glight.on.is_some_and(|on| on.on),
@@ -337,13 +380,13 @@ impl ApiGroup {
                 hue: None,
                 sat: None,
                 effect: None,
-                xy: None,
-                ct: None,
+                xy: glight.as_color_opt().map(Into::into),
+                ct: glight.as_mirek_opt(),
                 alert: ApiAlert::None,
                 colormode: None,
             },
             class: "Bedroom".to_string(),
-            group_type: ApiGroupType::Room,
+            group_type,
             recycle: false,
             sensors: vec![],
             state: json!({}),
@@ -448,7 +491,12 @@ pub struct ApiLight {
 impl ApiLight {
     #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
     #[must_use]
-    pub fn from_dev_and_light(uuid: &Uuid, dev: &api::Device, light: &api::Light) -> Self {
+    pub fn from_dev_and_light(
+        uuid: &Uuid,
+        dev: &api::Device,
+        light: &api::Light,
+        reachable: bool,
+    ) -> Self {
         let colormode = if light.color.is_some() {
             LightColorMode::Xy
         } else {
@@ -469,7 +517,7 @@ impl ApiLight {
                 alert: String::new(),
                 colormode: Some(colormode),
                 mode: "homeautomation".to_string(),
-                reachable: true,
+                reachable,
             },
             swupdate: SwUpdate::default(),
             name: light.metadata.name.clone(),
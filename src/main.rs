@@ -2,9 +2,10 @@ use std::io::Write;
 
 use tokio::task::JoinSet;
 
+use bifrost::backend::z2m::mqtt::Z2mMqttBackend;
 use bifrost::backend::z2m::Z2mBackend;
 use bifrost::backend::Backend;
-use bifrost::config;
+use bifrost::config::{self, Z2mServerKind};
 use bifrost::error::ApiResult;
 use bifrost::mdns;
 use bifrost::server;
@@ -87,16 +88,38 @@ async fn build_tasks(appstate: AppState) -> ApiResult<JoinSet<ApiResult<()>>> {
         appstate.res.clone(),
         appstate.updater(),
     ));
+    tasks.spawn(server::pending_expiry(appstate.res.clone()));
 
-    for (name, server) in &appstate.config().z2m.servers {
-        let client = Z2mBackend::new(
-            name.clone(),
-            server.clone(),
-            appstate.config(),
-            appstate.res.clone(),
-        )?;
-        let stream = appstate.res.lock().await.backend_event_stream();
-        tasks.spawn(client.run_forever(stream));
+    let config = appstate.config();
+
+    /* register every backend before any of them starts, so none can
+     * report being synced while another one is still unknown */
+    let mut streams = vec![];
+    for name in config.z2m.servers.keys() {
+        streams.push(appstate.res.lock().await.register_backend(name));
+    }
+
+    for ((name, server), stream) in config.z2m.servers.iter().zip(streams) {
+        match server.kind {
+            Z2mServerKind::Websocket => {
+                let client = Z2mBackend::new(
+                    name.clone(),
+                    server.clone(),
+                    appstate.config(),
+                    appstate.res.clone(),
+                )?;
+                tasks.spawn(client.run_forever(stream));
+            }
+            Z2mServerKind::Mqtt => {
+                let client = Z2mMqttBackend::new(
+                    name.clone(),
+                    server.clone(),
+                    appstate.config(),
+                    appstate.res.clone(),
+                )?;
+                tasks.spawn(client.run_forever(stream));
+            }
+        }
     }
 
     Ok(tasks)
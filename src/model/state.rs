@@ -105,6 +105,9 @@ pub struct State {
     aux: BTreeMap<Uuid, AuxData>,
     id_v1: IdMap,
     pub res: BTreeMap<Uuid, Resource>,
+    /// Resources no backend claimed, kept aside in case they come back
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    quarantine: BTreeMap<Uuid, Resource>,
 }
 
 impl State {
@@ -187,6 +190,7 @@ impl State {
             aux,
             id_v1,
             res,
+            quarantine: BTreeMap::new(),
         })
     }
 
@@ -241,6 +245,19 @@ impl State {
         Ok(())
     }
 
+    /// Hide a resource from the api, but keep its data (including aux data
+    /// and `id_v1`), so it can be restored by [`Self::unquarantine`].
+    pub fn quarantine(&mut self, id: &Uuid) -> ApiResult<()> {
+        let obj = self.res.remove(id).ok_or_else(|| ApiError::NotFound(*id))?;
+        self.quarantine.insert(*id, obj);
+        Ok(())
+    }
+
+    /// Take a resource back out of quarantine, if it is there
+    pub fn unquarantine(&mut self, id: &Uuid) -> Option<Resource> {
+        self.quarantine.remove(id)
+    }
+
     #[must_use]
     pub fn id_v1(&self, uuid: &Uuid) -> Option<u32> {
         self.id_v1.id(uuid)
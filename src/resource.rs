@@ -1,37 +1,57 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::io::{Read, Write};
 use std::sync::Arc;
 
 use serde_json::{json, Value};
-use tokio::sync::broadcast::{Receiver, Sender};
-use tokio::sync::Notify;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::{oneshot, Notify};
+use tokio::time::{Duration, Instant};
 use uuid::Uuid;
 
-use crate::backend::BackendRequest;
+use crate::backend::{BackendAck, BackendRequest, QueuedRequest};
+use crate::config::OrphanPolicy;
 use crate::error::{ApiError, ApiResult};
 use crate::hue::api::{
-    Bridge, BridgeHome, Device, DeviceArchetype, DeviceProductData, DeviceUpdate, Metadata, RType,
-    Resource, ResourceLink, ResourceRecord, RoomUpdate, TimeZone, ZigbeeConnectivity,
-    ZigbeeConnectivityStatus, ZigbeeDeviceDiscovery,
+    Bridge, BridgeHome, Device, DeviceArchetype, DeviceProductData, DeviceUpdate, GroupedLight,
+    Light, Metadata, RType, Resource, ResourceLink, ResourceRecord, Room, RoomUpdate, Scene,
+    TimeZone, ZigbeeConnectivity, ZigbeeConnectivityStatus, ZigbeeDeviceDiscovery,
+    ZigbeeDeviceDiscoveryStatus, Zone,
+};
+use crate::hue::api::{
+    ButtonUpdate, DevicePowerUpdate, GroupedLightUpdate, LightLevelUpdate, LightUpdate,
+    MotionUpdate, RelativeRotaryUpdate, SceneUpdate, TemperatureUpdate, Update,
+    ZigbeeConnectivityUpdate, ZigbeeDeviceDiscoveryUpdate, ZoneUpdate,
 };
-use crate::hue::api::{GroupedLightUpdate, LightUpdate, SceneUpdate, Update};
 use crate::hue::event::EventBlock;
 use crate::hue::version::SwVersion;
 use crate::model::state::{AuxData, State};
 use crate::server::hueevents::HueEventStream;
 
+/// A light update that has been applied locally, but not yet confirmed by
+/// the backend
+#[derive(Clone, Debug)]
+struct PendingLight {
+    previous: Light,
+    expires: Instant,
+}
+
 #[derive(Clone, Debug)]
 pub struct Resources {
     state: State,
     version: SwVersion,
     state_updates: Arc<Notify>,
-    backend_updates: Sender<Arc<BackendRequest>>,
+    backends: HashMap<String, UnboundedSender<QueuedRequest>>,
+    owners: HashMap<Uuid, String>,
+    synced: HashSet<String>,
+    orphans_checked: bool,
+    pending: HashMap<Uuid, PendingLight>,
     hue_event_stream: HueEventStream,
 }
 
 impl Resources {
     const MAX_SCENE_ID: u32 = 100;
     const HUE_EVENTS_BUFFER_SIZE: usize = 128;
+    const PENDING_TIMEOUT: Duration = Duration::from_secs(5);
 
     #[allow(clippy::new_without_default)]
     #[must_use]
@@ -40,7 +60,11 @@ impl Resources {
             state,
             version,
             state_updates: Arc::new(Notify::new()),
-            backend_updates: Sender::new(32),
+            backends: HashMap::new(),
+            owners: HashMap::new(),
+            synced: HashSet::new(),
+            orphans_checked: false,
+            pending: HashMap::new(),
             hue_event_stream: HueEventStream::new(Self::HUE_EVENTS_BUFFER_SIZE),
         }
     }
@@ -80,6 +104,7 @@ impl Resources {
         match obj {
             Resource::Light(light) => {
                 let upd = LightUpdate::new()
+                    .with_metadata(&light.metadata)
                     .with_brightness(light.dimming)
                     .with_on(light.on)
                     .with_color_temperature(light.as_mirek_opt())
@@ -91,7 +116,9 @@ impl Resources {
             Resource::GroupedLight(glight) => {
                 let upd = GroupedLightUpdate::new()
                     .with_on(glight.on)
-                    .with_brightness(glight.as_brightness_opt());
+                    .with_brightness(glight.as_brightness_opt())
+                    .with_color_temperature(glight.as_mirek_opt())
+                    .with_color_xy(glight.as_color_opt());
 
                 Ok(Some(Update::GroupedLight(upd)))
             }
@@ -108,10 +135,67 @@ impl Resources {
                 Ok(Some(Update::Device(upd)))
             }
             Resource::Room(room) => {
-                let upd = RoomUpdate::new().with_metadata(room.metadata.clone());
+                let upd = RoomUpdate::new()
+                    .with_metadata(room.metadata.clone())
+                    .with_children(Some(room.children.clone()));
 
                 Ok(Some(Update::Room(upd)))
             }
+            Resource::Zone(zone) => {
+                let upd = ZoneUpdate::new()
+                    .with_metadata(zone.metadata.clone())
+                    .with_children(Some(zone.children.clone()));
+
+                Ok(Some(Update::Zone(upd)))
+            }
+            Resource::Button(button) => {
+                let upd = ButtonUpdate::new().with_button(Some(button.button.clone()));
+
+                Ok(Some(Update::Button(upd)))
+            }
+            Resource::DevicePower(power) => {
+                let upd =
+                    DevicePowerUpdate::new().with_power_state(Some(power.power_state.clone()));
+
+                Ok(Some(Update::DevicePower(upd)))
+            }
+            Resource::ZigbeeConnectivity(zbc) => {
+                let upd = ZigbeeConnectivityUpdate::new().with_status(Some(zbc.status));
+
+                Ok(Some(Update::ZigbeeConnectivity(upd)))
+            }
+            Resource::ZigbeeDeviceDiscovery(zbdd) => {
+                let upd = ZigbeeDeviceDiscoveryUpdate::new().with_status(Some(zbdd.status));
+
+                Ok(Some(Update::ZigbeeDeviceDiscovery(upd)))
+            }
+            Resource::Motion(motion) => {
+                let upd = MotionUpdate::new()
+                    .with_enabled(Some(motion.enabled))
+                    .with_motion(Some(motion.motion.clone()));
+
+                Ok(Some(Update::Motion(upd)))
+            }
+            Resource::RelativeRotary(rotary) => {
+                let upd = RelativeRotaryUpdate::new()
+                    .with_relative_rotary(Some(rotary.relative_rotary.clone()));
+
+                Ok(Some(Update::RelativeRotary(upd)))
+            }
+            Resource::LightLevel(light_level) => {
+                let upd = LightLevelUpdate::new()
+                    .with_enabled(Some(light_level.enabled))
+                    .with_light(Some(light_level.light.clone()));
+
+                Ok(Some(Update::LightLevel(upd)))
+            }
+            Resource::Temperature(temp) => {
+                let upd = TemperatureUpdate::new()
+                    .with_enabled(Some(temp.enabled))
+                    .with_temperature(Some(temp.temperature.clone()));
+
+                Ok(Some(Update::Temperature(upd)))
+            }
             obj => Err(ApiError::UpdateUnsupported(obj.rtype())),
         }
     }
@@ -124,8 +208,19 @@ impl Resources {
     where
         for<'a> &'a mut T: TryFrom<&'a mut Resource, Error = ApiError>,
     {
+        let prev = self.state.get(id)?;
+        let mut next = prev.clone();
+        func((&mut next).try_into()?)?;
+
+        self.validate_links(&next, Some(prev))?;
+
+        let refresh = matches!(
+            next,
+            Resource::Light(_) | Resource::Room(_) | Resource::Zone(_)
+        );
+
         let obj = self.state.get_mut(id)?;
-        func(obj.try_into()?)?;
+        *obj = next;
 
         if let Some(delta) = Self::generate_update(obj)? {
             let id_v1 = self.state.id_v1(id);
@@ -135,6 +230,91 @@ impl Resources {
 
         self.state_updates.notify_one();
 
+        if refresh {
+            self.refresh_grouped_lights(id)?;
+        }
+
+        Ok(())
+    }
+
+    /// Find the member lights of a grouped light, from the children of the
+    /// room (devices) or zone (lights) that owns it. The grouped light of the
+    /// bridge home covers all lights.
+    fn grouped_light_members(&self, glight: &GroupedLight) -> Vec<ResourceLink> {
+        match self.state.try_get(&glight.owner.rid) {
+            Some(Resource::Room(room)) => room
+                .children
+                .iter()
+                .filter_map(|child| self.get::<Device>(child).ok()?.light_service())
+                .copied()
+                .collect(),
+            Some(Resource::Zone(zone)) => zone.children.clone(),
+            Some(Resource::BridgeHome(_)) => self
+                .state
+                .res
+                .iter()
+                .filter(|(_, res)| matches!(res, Resource::Light(_)))
+                .map(|(id, _)| RType::Light.link_to(*id))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Find the grouped lights that depend on the given light (those of the
+    /// rooms and zones containing it, and of the bridge home), or on the
+    /// given room or zone (its own grouped light)
+    fn dependent_grouped_lights(&self, id: &Uuid) -> Vec<Uuid> {
+        let glights: Vec<&ResourceLink> = match self.state.try_get(id) {
+            Some(Resource::Light(light)) => self
+                .state
+                .res
+                .values()
+                .filter_map(|obj| match obj {
+                    Resource::Room(room) if room.children.contains(&light.owner) => {
+                        room.grouped_light_service()
+                    }
+                    Resource::Zone(zone) if zone.children.iter().any(|child| child.rid == *id) => {
+                        zone.grouped_light_service()
+                    }
+                    Resource::BridgeHome(home) => home.grouped_light_service(),
+                    _ => None,
+                })
+                .collect(),
+            Some(Resource::Room(room)) => room.grouped_light_service().into_iter().collect(),
+            Some(Resource::Zone(zone)) => zone.grouped_light_service().into_iter().collect(),
+            _ => vec![],
+        };
+
+        glights.into_iter().map(|link| link.rid).collect()
+    }
+
+    /// Recompute the state of every grouped light that depends on the given
+    /// light, room or zone. Updates are only sent for grouped lights where
+    /// the aggregated state actually changed.
+    pub fn refresh_grouped_lights(&mut self, id: &Uuid) -> ApiResult<()> {
+        let mut changed = vec![];
+
+        for gid in self.dependent_grouped_lights(id) {
+            let Some(Resource::GroupedLight(glight)) = self.state.try_get(&gid) else {
+                continue;
+            };
+
+            let members = self.grouped_light_members(glight);
+            let lights: Vec<&Light> = members
+                .iter()
+                .filter_map(|link| self.get::<Light>(link).ok())
+                .collect();
+
+            let next = glight.aggregate(&lights);
+            if next.state_differs(glight) {
+                changed.push((gid, next));
+            }
+        }
+
+        for (gid, next) in changed {
+            self.update::<GroupedLight>(&gid, |glight| *glight = next)?;
+        }
+
         Ok(())
     }
 
@@ -148,6 +328,92 @@ impl Resources {
         })
     }
 
+    /// Apply a light update right away, instead of waiting for the backend
+    /// to report the new state. The state from before the first unconfirmed
+    /// update is kept, so it can be restored if the update never lands.
+    pub fn apply_light_update(&mut self, link: &ResourceLink, upd: &LightUpdate) -> ApiResult<()> {
+        let previous = self.get::<Light>(link)?.clone();
+        let expires = Instant::now() + Self::PENDING_TIMEOUT;
+
+        self.pending
+            .entry(link.rid)
+            .and_modify(|pending| pending.expires = expires)
+            .or_insert(PendingLight { previous, expires });
+
+        self.update::<Light>(&link.rid, |light| *light += upd.clone())
+    }
+
+    /// Apply a grouped light update to all member lights right away
+    pub fn apply_grouped_light_update(
+        &mut self,
+        link: &ResourceLink,
+        upd: &GroupedLightUpdate,
+    ) -> ApiResult<()> {
+        let members = self.grouped_light_members(self.get::<GroupedLight>(link)?);
+        let lupd = LightUpdate::from(upd);
+
+        for light in &members {
+            if self.state.try_get(&light.rid).is_some() {
+                self.apply_light_update(light, &lupd)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The backend reported the state of a light, so any pending update is
+    /// no longer needed.
+    pub fn reconcile_pending(&mut self, id: &Uuid) {
+        self.pending.remove(id);
+    }
+
+    /// Restore a light (or all lights of a grouped light) to the state from
+    /// before any pending updates, e.g. because the backend rejected them
+    pub fn rollback_pending(&mut self, link: &ResourceLink) -> ApiResult<()> {
+        let lights = if link.rtype == RType::GroupedLight {
+            self.grouped_light_members(self.get::<GroupedLight>(link)?)
+        } else {
+            vec![*link]
+        };
+
+        for light in &lights {
+            let Some(PendingLight { previous, .. }) = self.pending.remove(&light.rid) else {
+                continue;
+            };
+
+            self.update::<Light>(&light.rid, |light| {
+                light.on = previous.on;
+                light.dimming = previous.dimming;
+                light.color = previous.color;
+                light.color_temperature = previous.color_temperature;
+                light.gradient = previous.gradient;
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Roll back pending updates the backend has not confirmed in time
+    pub fn expire_pending(&mut self) -> ApiResult<()> {
+        let now = Instant::now();
+        let expired: Vec<ResourceLink> = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| pending.expires <= now)
+            .map(|(id, _)| RType::Light.link_to(*id))
+            .collect();
+
+        for link in &expired {
+            log::warn!(
+                "No state reported for light {}, rolling back pending update",
+                link.rid
+            );
+            self.rollback_pending(link)?;
+        }
+
+        Ok(())
+    }
+
     #[must_use]
     pub fn get_scenes_for_room(&self, id: &Uuid) -> Vec<Uuid> {
         self.state
@@ -181,6 +447,19 @@ impl Resources {
             return Ok(());
         }
 
+        let obj = self.state.unquarantine(&link.rid).map_or(obj, |old| {
+            log::info!("Restoring {link:?} from quarantine");
+            old
+        });
+
+        self.validate_links(&obj, None)?;
+
+        let refresh = match &obj {
+            Resource::GroupedLight(glight) => Some(glight.owner.rid),
+            Resource::Light(_) => Some(link.rid),
+            _ => None,
+        };
+
         self.state.insert(link.rid, obj);
 
         self.state_updates.notify_one();
@@ -191,12 +470,21 @@ impl Resources {
 
         self.hue_event_stream.hue_event(evt);
 
+        if let Some(id) = refresh {
+            self.refresh_grouped_lights(&id)?;
+        }
+
         Ok(())
     }
 
+    /// Delete a resource, along with everything it owns (services of a
+    /// device or group, scenes of a room or zone). Links to the deleted
+    /// resources are removed from the resources that list them.
     pub fn delete(&mut self, link: &ResourceLink) -> ApiResult<()> {
         log::info!("Deleting {link:?}..");
         self.state.remove(&link.rid)?;
+        self.owners.remove(&link.rid);
+        self.pending.remove(&link.rid);
 
         self.state_updates.notify_one();
 
@@ -204,9 +492,141 @@ impl Resources {
 
         self.hue_event_stream.hue_event(evt);
 
+        let owned: Vec<ResourceLink> = self
+            .state
+            .res
+            .iter()
+            .filter(|(_, obj)| obj.owner().is_some_and(|owner| owner.rid == link.rid))
+            .map(|(id, obj)| obj.rtype().link_to(*id))
+            .collect();
+
+        for child in &owned {
+            if self.state.try_get(&child.rid).is_some() {
+                self.delete(child)?;
+            }
+        }
+
+        self.strip_links_to(link)
+    }
+
+    /// Add `service` to a device, unless it is already listed. Devices loaded
+    /// from older state files can lack services added since then.
+    pub fn ensure_service(&mut self, link: &ResourceLink, service: ResourceLink) -> ApiResult<()> {
+        if self.get::<Device>(link)?.services.contains(&service) {
+            return Ok(());
+        }
+
+        log::debug!("Adding missing service {service:?} to {link:?}");
+        self.update::<Device>(&link.rid, |dev| dev.services.push(service))
+    }
+
+    /// Make sure the owner of `obj` (and the targets of its scene actions)
+    /// exist, and have the expected type. Links already present in `prev`
+    /// are not checked again, so resources loaded with broken links can
+    /// still be updated.
+    fn validate_links(&self, obj: &Resource, prev: Option<&Resource>) -> ApiResult<()> {
+        let mut links: Vec<&ResourceLink> = obj.owner().into_iter().collect();
+        if let Resource::Scene(scene) = obj {
+            links.extend(scene.actions.iter().map(|act| &act.target));
+        }
+
+        let known = prev.map(Resource::links).unwrap_or_default();
+
+        for link in links {
+            if known.contains(&link) {
+                continue;
+            }
+            let target = self.state.get(&link.rid)?;
+            if target.rtype() != link.rtype {
+                return Err(ApiError::WrongType(link.rtype, target.rtype()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Drop children of rooms, zones and the bridge home that still do not
+    /// exist once every backend has synced (e.g. z2m group members that
+    /// bifrost does not support). Until then, members may just not be
+    /// imported yet.
+    fn strip_missing_children(&mut self) -> ApiResult<()> {
+        let missing: HashSet<ResourceLink> = self
+            .state
+            .res
+            .values()
+            .filter_map(|obj| match obj {
+                Resource::Room(Room { children, .. })
+                | Resource::Zone(Zone { children, .. })
+                | Resource::BridgeHome(BridgeHome { children, .. }) => Some(children),
+                _ => None,
+            })
+            .flatten()
+            .filter(|child| self.state.try_get(&child.rid).is_none())
+            .copied()
+            .collect();
+
+        for link in &missing {
+            log::debug!("Dropping missing child {link:?}");
+            self.strip_links_to(link)?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove links to a deleted resource from every resource listing it
+    fn strip_links_to(&mut self, link: &ResourceLink) -> ApiResult<()> {
+        let mut changed = vec![];
+
+        for (id, obj) in &mut self.state.res {
+            if obj.remove_links_to(&link.rid) {
+                log::debug!(
+                    "Removed link to {link:?} from {:?}",
+                    obj.rtype().link_to(*id)
+                );
+                changed.push(*id);
+            }
+        }
+
+        for id in changed {
+            let obj = self.state.get(&id)?;
+            let refresh = matches!(obj, Resource::Room(_) | Resource::Zone(_));
+
+            if let Ok(Some(delta)) = Self::generate_update(obj) {
+                let id_v1 = self.state.id_v1(&id);
+                self.hue_event_stream
+                    .hue_event(EventBlock::update(&id, id_v1, delta)?);
+            }
+
+            if refresh {
+                self.refresh_grouped_lights(&id)?;
+            }
+        }
+
         Ok(())
     }
 
+    /// Find links that point to missing resources, or to resources of the
+    /// wrong type. Returns a list of (source, broken link) pairs.
+    #[must_use]
+    pub fn check(&self) -> Vec<(ResourceLink, ResourceLink)> {
+        let mut problems = vec![];
+
+        for (id, obj) in &self.state.res {
+            for link in obj.links() {
+                let valid = self
+                    .state
+                    .try_get(&link.rid)
+                    .is_some_and(|target| target.rtype() == link.rtype);
+
+                if !valid {
+                    problems.push((obj.rtype().link_to(*id), *link));
+                }
+            }
+        }
+
+        problems
+    }
+
     pub fn add_bridge(&mut self, bridge_id: String) -> ApiResult<()> {
         let link_bridge = RType::Bridge.deterministic(&bridge_id);
         let link_bridge_home = RType::BridgeHome.deterministic(format!("{bridge_id}HOME"));
@@ -223,6 +643,14 @@ impl Resources {
             usertest: None,
         };
 
+        /* the bridge id is the hex-encoded zigbee mac address of the bridge */
+        let mac_address = bridge_id
+            .as_bytes()
+            .chunks(2)
+            .map(String::from_utf8_lossy)
+            .collect::<Vec<_>>()
+            .join(":");
+
         let bridge = Bridge {
             bridge_id,
             owner: link_bridge_dev,
@@ -237,21 +665,26 @@ impl Resources {
             usertest: None,
         };
 
+        let link_bridge_home_glight = RType::GroupedLight.deterministic(link_bridge_home.rid);
+
         let bridge_home = BridgeHome {
             children: vec![link_bridge_dev],
-            services: vec![RType::GroupedLight.deterministic(link_bridge_home.rid)],
+            services: vec![link_bridge_home_glight],
         };
 
+        /* the bridge home grouped light covers every light, on all backends */
+        let bridge_home_glight = GroupedLight::new(link_bridge_home);
+
         let zbdd = ZigbeeDeviceDiscovery {
             owner: link_bridge_dev,
-            status: String::from("ready"),
+            status: ZigbeeDeviceDiscoveryStatus::Ready,
             action: Value::Null,
         };
 
         let zbc = ZigbeeConnectivity {
             owner: link_bridge_dev,
-            mac_address: String::from("11:22:33:44:55:66:77:88"),
-            status: ZigbeeConnectivityStatus::ConnectivityIssue,
+            mac_address,
+            status: ZigbeeConnectivityStatus::Connected,
             channel: Some(json!({
                 "status": "set",
                 "value": "channel_25",
@@ -263,6 +696,10 @@ impl Resources {
         self.add(&link_bridge, Resource::Bridge(bridge))?;
         self.add(&link_bridge_home_dev, Resource::Device(bridge_home_dev))?;
         self.add(&link_bridge_home, Resource::BridgeHome(bridge_home))?;
+        self.add(
+            &link_bridge_home_glight,
+            Resource::GroupedLight(bridge_home_glight),
+        )?;
         self.add(&link_zbdd, Resource::ZigbeeDeviceDiscovery(zbdd))?;
         self.add(&link_zbc, Resource::ZigbeeConnectivity(zbc))?;
 
@@ -328,15 +765,23 @@ impl Resources {
     fn id_v1_scope(&self, id: &Uuid, res: &Resource) -> Option<String> {
         let id = self.state.id_v1(id)?;
         match res {
+            /* The bridge home grouped light is the v1 "group 0" */
+            Resource::GroupedLight(glight) if glight.owner.rtype == RType::BridgeHome => {
+                Some(String::from("/groups/0"))
+            }
             Resource::GroupedLight(_) => Some(format!("/groups/{id}")),
             Resource::Light(_) => Some(format!("/lights/{id}")),
             Resource::Scene(_) => Some(format!("/scenes/{id}")),
 
-            /* Rooms map to their grouped_light service's id_v1 */
+            /* Rooms and zones map to their grouped_light service's id_v1 */
             Resource::Room(room) => room
                 .grouped_light_service()
                 .and_then(|glight| self.state.id_v1(&glight.rid))
                 .map(|id| format!("/groups/{id}")),
+            Resource::Zone(zone) => zone
+                .grouped_light_service()
+                .and_then(|glight| self.state.id_v1(&glight.rid))
+                .map(|id| format!("/groups/{id}")),
 
             /* Devices (that are lights) map to the light service's id_v1 */
             Resource::Device(dev) => dev
@@ -372,7 +817,6 @@ impl Resources {
             | Resource::Taurus(_)
             | Resource::Temperature(_)
             | Resource::ZigbeeConnectivity(_)
-            | Resource::Zone(_)
             | Resource::ZigbeeDeviceDiscovery(_) => None,
         }
     }
@@ -438,15 +882,267 @@ impl Resources {
     }
 
     #[must_use]
-    pub fn backend_event_stream(&self) -> Receiver<Arc<BackendRequest>> {
-        self.backend_updates.subscribe()
+    /// Register a backend, returning the queue of requests addressed to it
+    pub fn register_backend(&mut self, name: &str) -> UnboundedReceiver<QueuedRequest> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.backends.insert(name.to_string(), tx);
+        rx
     }
 
-    pub fn backend_request(&self, req: BackendRequest) -> ApiResult<()> {
-        log::debug!("z2m request: {req:#?}");
+    /// Called by a backend once it has imported its first full list of
+    /// devices and groups. When every backend has done so, any stored
+    /// resources that no backend claimed are cleaned up.
+    pub fn backend_synced(&mut self, backend: &str, policy: OrphanPolicy) -> ApiResult<()> {
+        self.synced.insert(backend.to_string());
+
+        if self.orphans_checked || !self.backends.keys().all(|name| self.synced.contains(name)) {
+            return Ok(());
+        }
+        self.orphans_checked = true;
+
+        self.strip_missing_children()?;
 
-        self.backend_updates.send(Arc::new(req))?;
+        let orphans = self.find_orphans();
+        if orphans.is_empty() {
+            log::info!("All stored resources are accounted for");
+            return Ok(());
+        }
+
+        let mut summary: BTreeMap<String, usize> = BTreeMap::new();
+        for link in &orphans {
+            log::debug!("Orphaned resource: {link:?}");
+            *summary.entry(format!("{:?}", link.rtype)).or_default() += 1;
+
+            /* may already be gone, along with its owner */
+            if self.state.try_get(&link.rid).is_none() {
+                continue;
+            }
+
+            match policy {
+                OrphanPolicy::Delete => self.delete(link)?,
+                OrphanPolicy::Quarantine => {
+                    self.state.quarantine(&link.rid)?;
+                    self.owners.remove(&link.rid);
+                    self.hue_event_stream.hue_event(EventBlock::delete(link)?);
+                }
+            }
+        }
+        self.state_updates.notify_one();
+
+        let details: Vec<String> = summary
+            .iter()
+            .map(|(rtype, count)| format!("{count} {rtype}"))
+            .collect();
+        log::info!(
+            "{} {} orphaned resources: {}",
+            match policy {
+                OrphanPolicy::Delete => "Deleted",
+                OrphanPolicy::Quarantine => "Quarantined",
+            },
+            orphans.len(),
+            details.join(", ")
+        );
 
         Ok(())
     }
+
+    /// Find stored resources that belong to devices and groups no backend
+    /// reported, along with the services that hang off them
+    fn find_orphans(&self) -> Vec<ResourceLink> {
+        let owned = |id: &Uuid| self.owners.contains_key(id);
+        let mut orphans = HashSet::new();
+
+        for (id, obj) in &self.state.res {
+            let link = obj.rtype().link_to(*id);
+            let services: &[ResourceLink] = match obj {
+                Resource::Device(dev)
+                    if dev.product_data.product_archetype != DeviceArchetype::BridgeV2
+                        && !owned(id) =>
+                {
+                    &dev.services
+                }
+                Resource::Room(room) if !owned(id) => &room.services,
+                Resource::Zone(zone) if !owned(id) => &zone.services,
+                Resource::Light(_) if !owned(id) => &[],
+                Resource::GroupedLight(glight)
+                    if glight.owner.rtype != RType::BridgeHome && !owned(id) =>
+                {
+                    &[]
+                }
+                Resource::Scene(scene) if !owned(&scene.group.rid) => &[],
+                _ => continue,
+            };
+
+            orphans.insert(link);
+            orphans.extend(
+                services
+                    .iter()
+                    .filter(|svc| self.state.try_get(&svc.rid).is_some()),
+            );
+        }
+
+        orphans.into_iter().collect()
+    }
+
+    /// Record `backend` as the owner of `link`, so requests for it are routed there
+    pub fn set_owner(&mut self, link: &ResourceLink, backend: &str) {
+        self.owners.insert(link.rid, backend.to_string());
+    }
+
+    /// Name of the backend that owns `link`, if any
+    #[must_use]
+    pub fn get_owner(&self, link: &ResourceLink) -> Option<&str> {
+        self.owners.get(&link.rid).map(String::as_str)
+    }
+
+    /// Find the resource whose owner is responsible for handling `req`, or
+    /// `None` if the request is for all backends
+    fn backend_request_target(&self, req: &BackendRequest) -> ApiResult<Option<Uuid>> {
+        let target = match req {
+            BackendRequest::LightUpdate(link, _)
+            | BackendRequest::LightRefresh(link)
+            | BackendRequest::RoomUpdate(link, _)
+            | BackendRequest::ZoneUpdate(link, _)
+            | BackendRequest::DeviceUpdate(link, _)
+            | BackendRequest::DeviceDelete(link, _) => link.rid,
+            /* new rooms and zones are created by the backend of their first member */
+            BackendRequest::RoomCreate(link, Room { children, .. })
+            | BackendRequest::ZoneCreate(link, Zone { children, .. }) => {
+                let Some(first) = children.first() else {
+                    /* empty ones can only go to the backend, if there is just one */
+                    if self.backends.len() == 1 {
+                        return Ok(None);
+                    }
+                    return Err(ApiError::NoBackendForEmptyGroup(*link));
+                };
+                first.rid
+            }
+            BackendRequest::SceneCreate(_, _, scene) => scene.group.rid,
+            BackendRequest::SceneUpdate(link, _) => self.get::<Scene>(link)?.group.rid,
+            BackendRequest::GroupedLightUpdate(link, _) => {
+                let owner = self.get::<GroupedLight>(link)?.owner;
+                /* every backend controls its own share of the bridge home lights */
+                if owner.rtype == RType::BridgeHome {
+                    return Ok(None);
+                }
+                owner.rid
+            }
+            BackendRequest::ZigbeeDeviceDiscoveryUpdate(_, _) => return Ok(None),
+            BackendRequest::Delete(link) => match link.rtype {
+                RType::Scene => self.get::<Scene>(link)?.group.rid,
+                _ => link.rid,
+            },
+        };
+        Ok(Some(target))
+    }
+
+    /// Queue `req` for the backend that owns it.
+    ///
+    /// The returned [`BackendAck`] can be awaited (after releasing the lock on
+    /// `self`) to learn whether the backend reported a failure.
+    pub fn backend_request(&self, req: BackendRequest) -> ApiResult<BackendAck> {
+        log::debug!("z2m request: {req:#?}");
+
+        let chans: Vec<_> = match self.backend_request_target(&req)? {
+            Some(target) => {
+                let chan = self
+                    .owners
+                    .get(&target)
+                    .and_then(|owner| self.backends.get(owner))
+                    .ok_or(ApiError::NoBackendOwner(target))?;
+                vec![chan]
+            }
+            None => self.backends.values().collect(),
+        };
+
+        let mut rxs = vec![];
+        if let Some((last, rest)) = chans.split_last() {
+            for chan in rest {
+                rxs.push(Self::queue_request(chan, req.clone())?);
+            }
+            rxs.push(Self::queue_request(last, req)?);
+        }
+
+        Ok(BackendAck::new(rxs))
+    }
+
+    fn queue_request(
+        chan: &UnboundedSender<QueuedRequest>,
+        req: BackendRequest,
+    ) -> ApiResult<oneshot::Receiver<ApiResult<()>>> {
+        let (reply, rx) = oneshot::channel();
+        chan.send(QueuedRequest { req, reply })?;
+        Ok(rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::config::OrphanPolicy;
+    use crate::error::ApiError;
+    use crate::hue::api::{BridgeHome, Device, RType, Resource, Room, RoomArchetype, RoomMetadata};
+    use crate::hue::version::SwVersion;
+    use crate::model::state::State;
+    use crate::resource::Resources;
+
+    #[test]
+    fn links_are_checked_and_cascaded() {
+        let mut res = Resources::new(SwVersion::new(0, String::new()), State::new());
+        res.init("001788fffe123456").unwrap();
+
+        let link_bridge = RType::Bridge.deterministic("001788fffe123456");
+        let link_bridge_dev = RType::Device.deterministic(link_bridge.rid);
+        let link_room = RType::Room.deterministic("room");
+
+        /* scenes cannot refer to a room that does not exist */
+        let scene = json!({
+            "actions": [],
+            "group": link_room,
+            "metadata": {"name": "scene"},
+            "speed": 0.5,
+            "status": null,
+        });
+        let link_scene = RType::Scene.deterministic("scene");
+        let obj = Resource::from_value(RType::Scene, scene).unwrap();
+        assert!(matches!(
+            res.add(&link_scene, obj.clone()),
+            Err(ApiError::NotFound(_))
+        ));
+
+        /* unknown children are kept until the backends have synced, then dropped */
+        let room = Room {
+            children: vec![link_bridge_dev, RType::Device.deterministic("missing")],
+            metadata: RoomMetadata::new(RoomArchetype::Home, "room"),
+            services: vec![],
+        };
+        res.add(&link_room, Resource::Room(room)).unwrap();
+        res.add(&link_scene, obj).unwrap();
+        assert_eq!(res.get::<Room>(&link_room).unwrap().children.len(), 2);
+        assert_eq!(res.check().len(), 1);
+
+        let _requests = res.register_backend("server1");
+        res.set_owner(&link_room, "server1");
+        res.backend_synced("server1", OrphanPolicy::Delete).unwrap();
+        assert_eq!(res.get::<Room>(&link_room).unwrap().children.len(), 1);
+        assert!(res.check().is_empty());
+
+        /* deleting the bridge device takes its services along, and leaves no dangling links */
+        res.delete(&link_bridge_dev).unwrap();
+        assert!(res.get::<Device>(&link_bridge_dev).is_err());
+        assert!(res.get_resource(RType::Bridge, &link_bridge.rid).is_err());
+        assert!(res.get::<Room>(&link_room).unwrap().children.is_empty());
+        assert!(res.check().is_empty());
+
+        /* deleting the room deletes its scenes */
+        res.delete(&link_room).unwrap();
+        assert!(res.get_resource(RType::Scene, &link_scene.rid).is_err());
+
+        let homes = res.get_resources_by_type(RType::BridgeHome);
+        let Resource::BridgeHome(BridgeHome { children, .. }) = &homes[0].obj else {
+            panic!("bridge home missing");
+        };
+        assert!(children.is_empty());
+    }
 }
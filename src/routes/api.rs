@@ -17,12 +17,13 @@ use crate::error::{ApiError, ApiResult};
 
 use crate::backend::BackendRequest;
 use crate::hue::api::{
-    Device, GroupedLight, GroupedLightUpdate, Light, LightUpdate, On, RType, ResourceLink, Room,
-    Scene, SceneActive, SceneStatus, SceneUpdate, V1Reply,
+    Device, GroupedLight, GroupedLightUpdate, Light, LightUpdate, On, RType, Resource,
+    ResourceLink, Room, Scene, SceneActive, SceneStatus, SceneUpdate, V1Reply, ZigbeeConnectivity,
+    Zone,
 };
 use crate::hue::legacy_api::{
     ApiGroup, ApiGroupActionUpdate, ApiLight, ApiLightStateUpdate, ApiResourceType, ApiScene,
-    ApiUserConfig, Capabilities, HueResult, NewUser, NewUserReply,
+    ApiUserConfig, Capabilities, HueError, HueResult, NewUser, NewUserReply,
 };
 use crate::resource::Resources;
 use crate::routes::extractor::Json;
@@ -42,6 +43,12 @@ async fn post_api(bytes: Bytes) -> ApiResult<impl IntoResponse> {
     Ok(Json(vec![HueResult::Success(res)]))
 }
 
+fn is_reachable(res: &MutexGuard<Resources>, dev: &Device) -> bool {
+    dev.zigbee_connectivity_service()
+        .and_then(|rl| res.get::<ZigbeeConnectivity>(rl).ok())
+        .map_or(true, ZigbeeConnectivity::is_connected)
+}
+
 fn get_lights(res: &MutexGuard<Resources>) -> ApiResult<HashMap<String, ApiLight>> {
     let mut lights = HashMap::new();
 
@@ -50,7 +57,7 @@ fn get_lights(res: &MutexGuard<Resources>) -> ApiResult<HashMap<String, ApiLight
         let dev = res.get::<Device>(&light.owner)?;
         lights.insert(
             res.get_id_v1(rr.id)?,
-            ApiLight::from_dev_and_light(&rr.id, dev, &light),
+            ApiLight::from_dev_and_light(&rr.id, dev, &light, is_reachable(res, dev)),
         );
     }
 
@@ -83,9 +90,39 @@ fn get_groups(res: &MutexGuard<Resources>) -> ApiResult<HashMap<String, ApiGroup
         );
     }
 
+    for rr in res.get_resources_by_type(RType::Zone) {
+        let zone: Zone = rr.obj.try_into()?;
+        let glight = zone
+            .grouped_light_service()
+            .ok_or(ApiError::NotFound(rr.id))?;
+
+        let glight = res.get::<GroupedLight>(glight)?;
+        let lights: Vec<String> = zone
+            .children
+            .iter()
+            .filter_map(|rl| res.get_id_v1(rl.rid).ok())
+            .collect();
+
+        rooms.insert(
+            res.get_id_v1(rr.id)?,
+            ApiGroup::from_lights_and_zone(glight, lights, zone),
+        );
+    }
+
     Ok(rooms)
 }
 
+/// Find the grouped light of the bridge home, which is "group 0" in the v1 api
+fn get_group_0(res: &MutexGuard<Resources>) -> ApiResult<ResourceLink> {
+    res.get_resources_by_type(RType::BridgeHome)
+        .into_iter()
+        .find_map(|rr| match rr.obj {
+            Resource::BridgeHome(home) => home.grouped_light_service().copied(),
+            _ => None,
+        })
+        .ok_or(ApiError::V1NotFound(0))
+}
+
 fn get_scenes(owner: &Uuid, res: &MutexGuard<Resources>) -> ApiResult<HashMap<String, ApiScene>> {
     let mut scenes = HashMap::new();
 
@@ -170,7 +207,9 @@ async fn get_api_user_resource_id(
             let light = lock.get::<Light>(&link)?;
             let dev = lock.get::<Device>(&light.owner)?;
 
-            json!(ApiLight::from_dev_and_light(&uuid, dev, light))
+            let reachable = is_reachable(&lock, dev);
+
+            json!(ApiLight::from_dev_and_light(&uuid, dev, light, reachable))
         }
         ApiResourceType::Scenes => {
             let lock = state.res.lock().await;
@@ -180,6 +219,17 @@ async fn get_api_user_resource_id(
 
             json!(ApiScene::from_scene(&lock, username, scene)?)
         }
+        ApiResourceType::Groups if id == 0 => {
+            let lock = state.res.lock().await;
+            let glight = lock.get::<GroupedLight>(&get_group_0(&lock)?)?;
+            let lights: Vec<String> = lock
+                .get_resources_by_type(RType::Light)
+                .iter()
+                .filter_map(|rr| lock.get_id_v1(rr.id).ok())
+                .collect();
+
+            json!(ApiGroup::from_lights_and_bridge_home(glight, lights))
+        }
         ApiResourceType::Groups => {
             let lock = state.res.lock().await;
             let groups = get_groups(&lock)?;
@@ -195,6 +245,12 @@ async fn get_api_user_resource_id(
     Ok(Json(result))
 }
 
+/// Report a failed backend request as a hue api v1 error
+fn v1_failure(address: &str, err: &ApiError) -> ApiResult<Json<Value>> {
+    let res = HueResult::<Value>::Error(HueError::internal_error(address, err.to_string()));
+    Ok(Json(serde_json::to_value(vec![res])?))
+}
+
 async fn put_api_user_resource_id(
     State(state): State<AppState>,
     Path((_username, resource, id, path)): Path<(String, ApiResourceType, u32, String)>,
@@ -207,7 +263,7 @@ async fn put_api_user_resource_id(
                 return Err(ApiError::V1NotFound(id))?;
             }
 
-            let lock = state.res.lock().await;
+            let mut lock = state.res.lock().await;
             let uuid = lock.from_id_v1(id)?;
             let link = ResourceLink::new(uuid, RType::Light);
             let updv1: ApiLightStateUpdate = serde_json::from_value(req)?;
@@ -218,9 +274,14 @@ async fn put_api_user_resource_id(
                 .with_color_temperature(updv1.ct)
                 .with_color_xy(updv1.xy.map(Into::into));
 
-            lock.backend_request(BackendRequest::LightUpdate(link, upd))?;
+            let ack = lock.backend_request(BackendRequest::LightUpdate(link, upd.clone()))?;
+            lock.apply_light_update(&link, &upd)?;
             drop(lock);
 
+            if let Err(err) = state.wait_for_ack_or_rollback(ack, &link).await {
+                return v1_failure(&format!("/lights/{id}/{path}"), &err);
+            }
+
             let reply = V1Reply::for_light(id, &path).with_light_state_update(&updv1)?;
 
             Ok(Json(reply.json()))
@@ -231,11 +292,18 @@ async fn put_api_user_resource_id(
                 return Err(ApiError::V1NotFound(id))?;
             }
 
-            let lock = state.res.lock().await;
-            let uuid = lock.from_id_v1(id)?;
-            let link = ResourceLink::new(uuid, RType::Room);
-            let room: &Room = lock.get(&link)?;
-            let glight = room.grouped_light_service().unwrap();
+            let mut lock = state.res.lock().await;
+            let glight = if id == 0 {
+                get_group_0(&lock)?
+            } else {
+                let uuid = lock.from_id_v1(id)?;
+                match lock.get_resource_by_id(&uuid)?.obj {
+                    Resource::Room(room) => room.grouped_light_service().copied(),
+                    Resource::Zone(zone) => zone.grouped_light_service().copied(),
+                    _ => None,
+                }
+                .ok_or(ApiError::V1NotFound(id))?
+            };
 
             let updv1: ApiGroupActionUpdate = serde_json::from_value(req)?;
 
@@ -247,10 +315,19 @@ async fn put_api_user_resource_id(
                         .with_color_xy(upd.xy.map(Into::into))
                         .with_color_temperature(upd.ct);
 
-                    lock.backend_request(BackendRequest::GroupedLightUpdate(*glight, updv2))?;
+                    let ack = lock.backend_request(BackendRequest::GroupedLightUpdate(
+                        glight,
+                        updv2.clone(),
+                    ))?;
+                    lock.apply_grouped_light_update(&glight, &updv2)?;
                     drop(lock);
 
-                    V1Reply::for_group(id, &path).with_light_state_update(&upd)?
+                    let reply = V1Reply::for_group(id, &path).with_light_state_update(&upd)?;
+                    if let Err(err) = state.wait_for_ack_or_rollback(ack, &glight).await {
+                        return v1_failure(&format!("/groups/{id}/{path}"), &err);
+                    }
+
+                    reply
                 }
                 ApiGroupActionUpdate::GroupUpdate(upd) => {
                     let scene_id = upd.scene.parse()?;
@@ -260,10 +337,15 @@ async fn put_api_user_resource_id(
                         active: SceneActive::Static,
                         last_recall: None,
                     }));
-                    lock.backend_request(BackendRequest::SceneUpdate(rlink, updv2))?;
+                    let ack = lock.backend_request(BackendRequest::SceneUpdate(rlink, updv2))?;
                     drop(lock);
 
-                    V1Reply::for_group(id, &path).add("scene", upd.scene)?
+                    let reply = V1Reply::for_group(id, &path).add("scene", upd.scene)?;
+                    if let Err(err) = state.wait_for_ack(ack).await {
+                        return v1_failure(&format!("/groups/{id}/{path}"), &err);
+                    }
+
+                    reply
                 }
             };
 
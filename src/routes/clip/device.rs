@@ -1,11 +1,13 @@
-use axum::extract::{Path, State};
+use axum::extract::{Path, Query, State};
 use axum::routing::{get, put};
 use axum::Router;
 
+use serde::Deserialize;
 use serde_json::Value;
 use uuid::Uuid;
 
-use crate::hue::api::{Device, DeviceUpdate, RType, V2Reply};
+use crate::backend::BackendRequest;
+use crate::hue::api::{Device, DeviceUpdate, Light, RType, V2Reply};
 use crate::routes::clip::generic::get_resource;
 use crate::routes::clip::ApiV2Result;
 use crate::routes::extractor::Json;
@@ -23,11 +25,63 @@ async fn put_device(
 
     let upd: DeviceUpdate = serde_json::from_value(put)?;
 
-    state
-        .res
-        .lock()
-        .await
-        .update::<Device>(&id, |obj| *obj += upd)?;
+    let mut lock = state.res.lock().await;
+
+    lock.update::<Device>(&id, |obj| *obj += upd.clone())?;
+
+    let Some(name) = upd.metadata.as_ref().and_then(|md| md.name.clone()) else {
+        return V2Reply::ok(rlink);
+    };
+
+    /* keep the light name in sync with the device */
+    if let Some(light) = lock.get::<Device>(&rlink)?.light_service().copied() {
+        lock.update::<Light>(&light.rid, |obj| obj.metadata.name = name)?;
+    }
+
+    /* only forward the rename, if the owning z2m server syncs names */
+    let config = state.config();
+    let sync_names = lock
+        .get_owner(&rlink)
+        .and_then(|owner| config.z2m.servers.get(owner))
+        .is_some_and(|server| server.sync_names);
+
+    if !sync_names {
+        return V2Reply::ok(rlink);
+    }
+
+    let ack = lock.backend_request(BackendRequest::DeviceUpdate(rlink, upd))?;
+
+    drop(lock);
+
+    state.wait_for_ack(ack).await?;
+
+    V2Reply::ok(rlink)
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct DeleteParams {
+    /// Remove the device, even if it does not respond
+    #[serde(default)]
+    force: bool,
+}
+
+async fn delete_device(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<DeleteParams>,
+) -> ApiV2Result {
+    log::info!("DELETE device/{id}");
+
+    let rlink = RType::Device.link_to(id);
+    let lock = state.res.lock().await;
+
+    lock.get::<Device>(&rlink)?;
+
+    let ack = lock.backend_request(BackendRequest::DeviceDelete(rlink, params.force))?;
+
+    drop(lock);
+
+    state.wait_for_ack(ack).await?;
 
     V2Reply::ok(rlink)
 }
@@ -35,5 +89,62 @@ async fn put_device(
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/", get(|state| get_resource(state, Path(RType::Device))))
-        .route("/{id}", put(put_device))
+        .route("/{id}", put(put_device).delete(delete_device))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::extract::{Path, State};
+    use serde_json::json;
+
+    use crate::hue::api::{Device, RType};
+    use crate::hue::version::SwVersion;
+    use crate::model::state::State as ResState;
+    use crate::resource::Resources;
+    use crate::routes::extractor::Json;
+    use crate::server::appstate::AppState;
+
+    #[test]
+    fn rename_bridge_device() {
+        let config = serde_json::from_value(json!({
+            "bridge": {
+                "name": "Bifrost",
+                "mac": "00:11:22:33:44:55",
+                "ipaddress": "10.0.0.2",
+                "http_port": 80,
+                "https_port": 443,
+                "netmask": "255.255.255.0",
+                "gateway": "10.0.0.1",
+                "timezone": "UTC",
+            },
+            "z2m": {
+                "server1": {"url": "ws://10.0.0.100:8080", "sync_names": true},
+            },
+            "bifrost": {"state_file": "state.yaml", "cert_file": "cert.pem"},
+        }))
+        .unwrap();
+
+        let mut res = Resources::new(SwVersion::new(0, String::new()), ResState::new());
+        res.init("001788fffe123456").unwrap();
+        let _requests = res.register_backend("server1");
+        let state = AppState::new(config, res);
+
+        /* the bridge device has no backend, so the rename is only stored */
+        let link_bridge = RType::Bridge.deterministic("001788fffe123456");
+        let link = RType::Device.deterministic(link_bridge.rid);
+        let put = json!({"metadata": {"name": "Bridge"}});
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        rt.block_on(super::put_device(
+            State(state.clone()),
+            Path(link.rid),
+            Json(put),
+        ))
+        .unwrap();
+
+        let res = rt.block_on(state.res.lock());
+        assert_eq!(res.get::<Device>(&link).unwrap().metadata.name, "Bridge");
+    }
 }
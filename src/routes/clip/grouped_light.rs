@@ -20,17 +20,20 @@ async fn put_grouped_light(
     log::debug!("json data\n{}", serde_json::to_string_pretty(&put)?);
 
     let rlink = RType::GroupedLight.link_to(id);
-    let lock = state.res.lock().await;
+    let mut lock = state.res.lock().await;
     lock.get::<GroupedLight>(&rlink)?;
 
     log::info!("PUT grouped_light/{id}: updating");
 
     let upd: GroupedLightUpdate = serde_json::from_value(put)?;
 
-    lock.backend_request(BackendRequest::GroupedLightUpdate(rlink, upd))?;
+    let ack = lock.backend_request(BackendRequest::GroupedLightUpdate(rlink, upd.clone()))?;
+    lock.apply_grouped_light_update(&rlink, &upd)?;
 
     drop(lock);
 
+    state.wait_for_ack_or_rollback(ack, &rlink).await?;
+
     V2Reply::ok(rlink)
 }
 
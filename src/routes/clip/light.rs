@@ -20,16 +20,19 @@ async fn put_light(
     log::debug!("json data\n{}", serde_json::to_string_pretty(&put)?);
 
     let rlink = RType::Light.link_to(id);
-    let lock = state.res.lock().await;
+    let mut lock = state.res.lock().await;
 
     let _ = lock.get::<Light>(&rlink)?;
 
     let upd: LightUpdate = serde_json::from_value(put)?;
 
-    lock.backend_request(BackendRequest::LightUpdate(rlink, upd))?;
+    let ack = lock.backend_request(BackendRequest::LightUpdate(rlink, upd.clone()))?;
+    lock.apply_light_update(&rlink, &upd)?;
 
     drop(lock);
 
+    state.wait_for_ack_or_rollback(ack, &rlink).await?;
+
     V2Reply::ok(rlink)
 }
 
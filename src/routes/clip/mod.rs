@@ -2,7 +2,10 @@ pub mod device;
 pub mod generic;
 pub mod grouped_light;
 pub mod light;
+pub mod room;
 pub mod scene;
+pub mod zigbee_device_discovery;
+pub mod zone;
 
 use axum::Router;
 use serde::Serialize;
@@ -42,5 +45,11 @@ pub fn router() -> Router<AppState> {
         .nest("/light", light::router())
         .nest("/device", device::router())
         .nest("/grouped_light", grouped_light::router())
+        .nest("/room", room::router())
+        .nest(
+            "/zigbee_device_discovery",
+            zigbee_device_discovery::router(),
+        )
+        .nest("/zone", zone::router())
         .merge(generic::router())
 }
@@ -0,0 +1,103 @@
+use axum::extract::{Path, State};
+use axum::routing::get;
+use axum::Router;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::backend::BackendRequest;
+use crate::hue::api::{Device, RType, Resource, Room, RoomUpdate, V2Reply};
+use crate::routes::clip::generic::get_resource;
+use crate::routes::clip::ApiV2Result;
+use crate::routes::extractor::Json;
+use crate::server::appstate::AppState;
+
+async fn post_room(State(state): State<AppState>, Json(req): Json<Value>) -> ApiV2Result {
+    log::info!("POST room");
+    log::debug!("json data\n{}", serde_json::to_string_pretty(&req)?);
+
+    let mut room: Room = serde_json::from_value(req)?;
+    room.services.clear();
+
+    let rlink = RType::Room.link_to(Uuid::new_v4());
+    let mut lock = state.res.lock().await;
+
+    for child in &room.children {
+        lock.get::<Device>(child)?;
+    }
+
+    let ack = lock.backend_request(BackendRequest::RoomCreate(rlink, room.clone()))?;
+
+    /* the room is populated once z2m reports the members of the new group */
+    room.children.clear();
+    lock.add(&rlink, Resource::Room(room))?;
+
+    drop(lock);
+
+    state.wait_for_ack(ack).await?;
+
+    V2Reply::ok(rlink)
+}
+
+async fn get_room(State(state): State<AppState>, Path(id): Path<Uuid>) -> ApiV2Result {
+    V2Reply::ok(state.res.lock().await.get_resource(RType::Room, &id)?)
+}
+
+async fn put_room(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(put): Json<Value>,
+) -> ApiV2Result {
+    log::info!("PUT room/{id}");
+    log::debug!("json data\n{}", serde_json::to_string_pretty(&put)?);
+
+    let rlink = RType::Room.link_to(id);
+    let upd: RoomUpdate = serde_json::from_value(put)?;
+
+    let mut lock = state.res.lock().await;
+
+    if let Some(md) = upd.metadata.clone() {
+        lock.update::<Room>(&id, |room| room.metadata += md)?;
+    }
+
+    let Some(children) = &upd.children else {
+        return V2Reply::ok(rlink);
+    };
+
+    for child in children {
+        lock.get::<Device>(child)?;
+    }
+
+    let ack = lock.backend_request(BackendRequest::RoomUpdate(rlink, upd))?;
+
+    drop(lock);
+
+    state.wait_for_ack(ack).await?;
+
+    V2Reply::ok(rlink)
+}
+
+async fn delete_room(State(state): State<AppState>, Path(id): Path<Uuid>) -> ApiV2Result {
+    log::info!("DELETE room/{id}");
+
+    let rlink = RType::Room.link_to(id);
+    let lock = state.res.lock().await;
+
+    lock.get::<Room>(&rlink)?;
+
+    let ack = lock.backend_request(BackendRequest::Delete(rlink))?;
+
+    drop(lock);
+
+    state.wait_for_ack(ack).await?;
+
+    V2Reply::ok(rlink)
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/",
+            get(|state| get_resource(state, Path(RType::Room))).post(post_room),
+        )
+        .route("/{id}", get(get_room).put(put_room).delete(delete_room))
+}
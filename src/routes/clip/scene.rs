@@ -7,7 +7,7 @@ use uuid::Uuid;
 
 use crate::backend::BackendRequest;
 use crate::error::{ApiError, ApiResult};
-use crate::hue::api::{RType, Resource, Scene, SceneUpdate, V2Reply};
+use crate::hue::api::{Light, RType, Resource, Scene, SceneUpdate, V2Reply};
 use crate::routes::clip::generic::get_resource;
 use crate::routes::clip::ApiV2Result;
 use crate::routes::extractor::Json;
@@ -23,14 +23,21 @@ async fn post_scene(
 
     let lock = state.res.lock().await;
 
+    /* the scene is added by the backend, so check its lights up front */
+    for act in &scene.actions {
+        lock.get::<Light>(&act.target)?;
+    }
+
     let sid = lock.get_next_scene_id(&scene.group)?;
 
     let link_scene = RType::Scene.deterministic((scene.group.rid, sid));
 
-    lock.backend_request(BackendRequest::SceneCreate(link_scene, sid, scene))?;
+    let ack = lock.backend_request(BackendRequest::SceneCreate(link_scene, sid, scene))?;
 
     drop(lock);
 
+    state.wait_for_ack(ack).await?;
+
     V2Reply::ok(link_scene)
 }
 
@@ -53,11 +60,17 @@ async fn put_scene(
         lock.update::<Scene>(&id, |scn| scn.metadata += md.clone())?;
     }
 
+    if let Some(actions) = &upd.actions {
+        lock.update::<Scene>(&id, |scn| scn.actions.clone_from(actions))?;
+    }
+
     let _scene = lock.get::<Scene>(&rlink)?;
 
-    lock.backend_request(BackendRequest::SceneUpdate(rlink, upd))?;
+    let ack = lock.backend_request(BackendRequest::SceneUpdate(rlink, upd))?;
     drop(lock);
 
+    state.wait_for_ack(ack).await?;
+
     V2Reply::ok(rlink)
 }
 
@@ -70,10 +83,12 @@ async fn delete_scene(State(state): State<AppState>, Path(id): Path<Uuid>) -> Ap
 
     match res.obj {
         Resource::Scene(_) => {
-            lock.backend_request(BackendRequest::Delete(link))?;
+            let ack = lock.backend_request(BackendRequest::Delete(link))?;
 
             drop(lock);
 
+            state.wait_for_ack(ack).await?;
+
             V2Reply::ok(link)
         }
         _ => Err(ApiError::DeleteDenied(id))?,
@@ -0,0 +1,61 @@
+use axum::extract::{Path, State};
+use axum::routing::get;
+use axum::Router;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::backend::BackendRequest;
+use crate::hue::api::{RType, V2Reply, ZigbeeDeviceDiscovery, ZigbeeDeviceDiscoveryUpdate};
+use crate::routes::clip::generic::get_resource;
+use crate::routes::clip::ApiV2Result;
+use crate::routes::extractor::Json;
+use crate::server::appstate::AppState;
+
+async fn put_zigbee_device_discovery(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(put): Json<Value>,
+) -> ApiV2Result {
+    log::info!("PUT zigbee_device_discovery/{id}");
+    log::debug!("json data\n{}", serde_json::to_string_pretty(&put)?);
+
+    let rlink = RType::ZigbeeDeviceDiscovery.link_to(id);
+    let lock = state.res.lock().await;
+
+    lock.get::<ZigbeeDeviceDiscovery>(&rlink)?;
+
+    let upd: ZigbeeDeviceDiscoveryUpdate = serde_json::from_value(put)?;
+
+    let ack = lock.backend_request(BackendRequest::ZigbeeDeviceDiscoveryUpdate(rlink, upd))?;
+
+    drop(lock);
+
+    state.wait_for_ack(ack).await?;
+
+    V2Reply::ok(rlink)
+}
+
+async fn get_zigbee_device_discovery(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> ApiV2Result {
+    V2Reply::ok(
+        state
+            .res
+            .lock()
+            .await
+            .get_resource(RType::ZigbeeDeviceDiscovery, &id)?,
+    )
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/",
+            get(|state| get_resource(state, Path(RType::ZigbeeDeviceDiscovery))),
+        )
+        .route(
+            "/{id}",
+            get(get_zigbee_device_discovery).put(put_zigbee_device_discovery),
+        )
+}
@@ -0,0 +1,103 @@
+use axum::extract::{Path, State};
+use axum::routing::get;
+use axum::Router;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::backend::BackendRequest;
+use crate::hue::api::{Light, RType, Resource, V2Reply, Zone, ZoneUpdate};
+use crate::routes::clip::generic::get_resource;
+use crate::routes::clip::ApiV2Result;
+use crate::routes::extractor::Json;
+use crate::server::appstate::AppState;
+
+async fn post_zone(State(state): State<AppState>, Json(req): Json<Value>) -> ApiV2Result {
+    log::info!("POST zone");
+    log::debug!("json data\n{}", serde_json::to_string_pretty(&req)?);
+
+    let mut zone: Zone = serde_json::from_value(req)?;
+    zone.services.clear();
+
+    let rlink = RType::Zone.link_to(Uuid::new_v4());
+    let mut lock = state.res.lock().await;
+
+    for child in &zone.children {
+        lock.get::<Light>(child)?;
+    }
+
+    let ack = lock.backend_request(BackendRequest::ZoneCreate(rlink, zone.clone()))?;
+
+    /* the zone is populated once z2m reports the members of the new group */
+    zone.children.clear();
+    lock.add(&rlink, Resource::Zone(zone))?;
+
+    drop(lock);
+
+    state.wait_for_ack(ack).await?;
+
+    V2Reply::ok(rlink)
+}
+
+async fn get_zone(State(state): State<AppState>, Path(id): Path<Uuid>) -> ApiV2Result {
+    V2Reply::ok(state.res.lock().await.get_resource(RType::Zone, &id)?)
+}
+
+async fn put_zone(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(put): Json<Value>,
+) -> ApiV2Result {
+    log::info!("PUT zone/{id}");
+    log::debug!("json data\n{}", serde_json::to_string_pretty(&put)?);
+
+    let rlink = RType::Zone.link_to(id);
+    let upd: ZoneUpdate = serde_json::from_value(put)?;
+
+    let mut lock = state.res.lock().await;
+
+    if let Some(md) = upd.metadata.clone() {
+        lock.update::<Zone>(&id, |zone| zone.metadata += md)?;
+    }
+
+    let Some(children) = &upd.children else {
+        return V2Reply::ok(rlink);
+    };
+
+    for child in children {
+        lock.get::<Light>(child)?;
+    }
+
+    let ack = lock.backend_request(BackendRequest::ZoneUpdate(rlink, upd))?;
+
+    drop(lock);
+
+    state.wait_for_ack(ack).await?;
+
+    V2Reply::ok(rlink)
+}
+
+async fn delete_zone(State(state): State<AppState>, Path(id): Path<Uuid>) -> ApiV2Result {
+    log::info!("DELETE zone/{id}");
+
+    let rlink = RType::Zone.link_to(id);
+    let lock = state.res.lock().await;
+
+    lock.get::<Zone>(&rlink)?;
+
+    let ack = lock.backend_request(BackendRequest::Delete(rlink))?;
+
+    drop(lock);
+
+    state.wait_for_ack(ack).await?;
+
+    V2Reply::ok(rlink)
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/",
+            get(|state| get_resource(state, Path(RType::Zone))).post(post_zone),
+        )
+        .route("/{id}", get(get_zone).put(put_zone).delete(delete_zone))
+}
@@ -29,6 +29,8 @@ impl IntoResponse for ApiError {
             Self::WrongType(_, _) => StatusCode::NOT_ACCEPTABLE,
             Self::DeleteDenied(_) => StatusCode::FORBIDDEN,
             Self::V1CreateUnsupported(_) => StatusCode::NOT_IMPLEMENTED,
+            Self::BackendOffline(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::NoBackendForEmptyGroup(_) => StatusCode::BAD_REQUEST,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         };
 
@@ -8,9 +8,11 @@ use chrono::Utc;
 use tokio::sync::Mutex;
 use uuid::Uuid;
 
+use crate::backend::BackendAck;
 use crate::config::AppConfig;
 use crate::error::{ApiError, ApiResult};
 use crate::hue;
+use crate::hue::api::ResourceLink;
 use crate::hue::legacy_api::{ApiConfig, ApiShortConfig, Whitelist};
 use crate::model::state::{State, StateVersion};
 use crate::resource::Resources;
@@ -57,6 +59,10 @@ impl AppState {
                 }
             };
             res = Resources::new(swversion, state);
+
+            for (source, link) in res.check() {
+                log::warn!("State file: {source:?} has a broken link to {link:?}");
+            }
         } else {
             log::debug!("No state file found, initializing..");
             res = Resources::new(swversion, State::new());
@@ -69,6 +75,18 @@ impl AppState {
         Ok(Self { conf, upd, res })
     }
 
+    /// App state around existing resources, without loading files or
+    /// fetching the firmware version
+    #[cfg(test)]
+    #[must_use]
+    pub fn new(config: AppConfig, res: Resources) -> Self {
+        Self {
+            conf: Arc::new(config),
+            upd: Arc::new(Mutex::new(VersionUpdater::new())),
+            res: Arc::new(Mutex::new(res)),
+        }
+    }
+
     pub async fn tls_config(&self) -> ApiResult<RustlsConfig> {
         let certfile = &self.conf.bifrost.cert_file;
 
@@ -83,6 +101,30 @@ impl AppState {
         self.conf.clone()
     }
 
+    /// Wait for the outcome of a backend request, if `wait_for_ack` is enabled
+    pub async fn wait_for_ack(&self, ack: BackendAck) -> ApiResult<()> {
+        if self.conf.bifrost.wait_for_ack {
+            ack.wait().await
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Like [`Self::wait_for_ack`], for a light or grouped light update that
+    /// was already applied locally. If the backend reports a failure, the
+    /// local state is rolled back.
+    pub async fn wait_for_ack_or_rollback(
+        &self,
+        ack: BackendAck,
+        link: &ResourceLink,
+    ) -> ApiResult<()> {
+        let res = self.wait_for_ack(ack).await;
+        if res.is_err() {
+            self.res.lock().await.rollback_pending(link)?;
+        }
+        res
+    }
+
     #[must_use]
     pub fn updater(&self) -> Arc<Mutex<VersionUpdater>> {
         self.upd.clone()
@@ -162,3 +162,15 @@ pub async fn version_updater(
         }
     }
 }
+
+pub async fn pending_expiry(res: Arc<Mutex<Resources>>) -> ApiResult<()> {
+    const INTERVAL: Duration = Duration::from_secs(1);
+    let mut interval = tokio::time::interval(INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        interval.tick().await;
+
+        res.lock().await.expire_pending()?;
+    }
+}
@@ -0,0 +1,195 @@
+use crate::hue::api::{ButtonEvent, RotaryDirection};
+use crate::z2m::api::Device;
+
+/// Source of rotation events on a rotary control
+#[derive(Copy, Debug, Clone, PartialEq, Eq)]
+pub enum RotaryKind {
+    /// `dial_rotate_{left,right}_{step,slow,fast}` actions (e.g. Hue tap dial)
+    Dial,
+    /// `brightness_step_{up,down}` actions (e.g. Lutron Aurora)
+    BrightnessStep,
+}
+
+impl RotaryKind {
+    /// Find the kind of rotary control on a device, if any.
+    ///
+    /// Some devices (like the Hue tap dial) report each turn both as a dial
+    /// rotation and as a brightness step, so the dial actions are preferred.
+    #[must_use]
+    pub fn from_device(dev: &Device) -> Option<Self> {
+        let values = dev.expose_action_values();
+        if values.iter().any(|val| val.starts_with("dial_rotate_")) {
+            Some(Self::Dial)
+        } else if values.contains(&"brightness_step_up") {
+            Some(Self::BrightnessStep)
+        } else {
+            None
+        }
+    }
+
+    /// Parse a z2m action into a rotation direction, if it is a rotation of
+    /// this kind
+    #[must_use]
+    pub fn parse_action(self, action: &str) -> Option<RotaryDirection> {
+        match self {
+            Self::Dial => {
+                let rotation = action.strip_prefix("dial_rotate_")?;
+                if rotation.starts_with("right_") {
+                    Some(RotaryDirection::ClockWise)
+                } else if rotation.starts_with("left_") {
+                    Some(RotaryDirection::CounterClockWise)
+                } else {
+                    None
+                }
+            }
+            Self::BrightnessStep => match action {
+                "brightness_step_up" => Some(RotaryDirection::ClockWise),
+                "brightness_step_down" => Some(RotaryDirection::CounterClockWise),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Layout of the controls on a switch-like device
+#[derive(Debug, Clone)]
+pub struct SwitchLayout {
+    pub buttons: Vec<String>,
+    pub rotary: Option<RotaryKind>,
+}
+
+impl SwitchLayout {
+    #[must_use]
+    pub fn from_device(dev: &Device) -> Self {
+        Self {
+            buttons: button_names(dev),
+            rotary: RotaryKind::from_device(dev),
+        }
+    }
+}
+
+/// Button layouts for known models.
+///
+/// Each entry is the button name used as prefix in z2m `action` values (e.g.
+/// `up` for `up_press`, `up_hold_release`, etc). The order of the buttons
+/// determines the hue `control_id` (starting at 1).
+#[must_use]
+pub fn button_layout(model_id: &str) -> Option<&'static [&'static str]> {
+    let layout: &[&str] = match model_id {
+        /* Hue dimmer switch (v1 and v2) */
+        "RWL020" | "RWL021" | "RWL022" => &["on", "up", "down", "off"],
+
+        /* Hue wall switch module */
+        "RDM001" => &["left", "right"],
+
+        /* Hue tap dial switch */
+        "RDM002" => &["button_1", "button_2", "button_3", "button_4"],
+
+        _ => return None,
+    };
+    Some(layout)
+}
+
+/// Split a z2m button action (e.g. `up_hold_release`) into the button name
+/// (`up`) and the corresponding hue button event.
+#[must_use]
+pub fn parse_button_action(action: &str) -> Option<(&str, ButtonEvent)> {
+    /* longest suffixes first, since "_press" is also a suffix of "_press_release" */
+    const SUFFIXES: &[(&str, ButtonEvent)] = &[
+        ("_press_release", ButtonEvent::ShortRelease),
+        ("_hold_release", ButtonEvent::LongRelease),
+        ("_press", ButtonEvent::InitialPress),
+        ("_hold", ButtonEvent::Repeat),
+    ];
+
+    SUFFIXES.iter().find_map(|(suffix, event)| {
+        action
+            .strip_suffix(suffix)
+            .filter(|button| !button.is_empty())
+            .map(|button| (button, *event))
+    })
+}
+
+/// Find the names of all buttons on a device, in `control_id` order.
+///
+/// Uses the known layout for the model if available. Otherwise, the buttons
+/// are guessed from the values of the `action` expose.
+#[must_use]
+pub fn button_names(dev: &Device) -> Vec<String> {
+    if let Some(layout) = dev.model_id.as_deref().and_then(button_layout) {
+        return layout.iter().map(ToString::to_string).collect();
+    }
+
+    let mut names: Vec<String> = vec![];
+    for value in dev.expose_action_values() {
+        if let Some((button, _)) = parse_button_action(value) {
+            if !names.iter().any(|name| name == button) {
+                names.push(button.to_string());
+            }
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hue::api::{ButtonEvent, RotaryDirection};
+    use crate::z2m::action::{parse_button_action, RotaryKind};
+
+    #[test]
+    fn parse_dimmer_actions() {
+        assert_eq!(
+            parse_button_action("on_press"),
+            Some(("on", ButtonEvent::InitialPress))
+        );
+        assert_eq!(
+            parse_button_action("up_hold"),
+            Some(("up", ButtonEvent::Repeat))
+        );
+        assert_eq!(
+            parse_button_action("down_press_release"),
+            Some(("down", ButtonEvent::ShortRelease))
+        );
+        assert_eq!(
+            parse_button_action("off_hold_release"),
+            Some(("off", ButtonEvent::LongRelease))
+        );
+    }
+
+    #[test]
+    fn parse_tap_dial_actions() {
+        assert_eq!(
+            parse_button_action("button_4_press_release"),
+            Some(("button_4", ButtonEvent::ShortRelease))
+        );
+    }
+
+    #[test]
+    fn parse_non_button_actions() {
+        assert_eq!(parse_button_action("brightness_step_up"), None);
+        assert_eq!(parse_button_action("press"), None);
+        assert_eq!(parse_button_action("_press"), None);
+        assert_eq!(parse_button_action(""), None);
+    }
+
+    #[test]
+    fn parse_rotary_actions() {
+        assert_eq!(
+            RotaryKind::Dial.parse_action("dial_rotate_right_fast"),
+            Some(RotaryDirection::ClockWise)
+        );
+        assert_eq!(
+            RotaryKind::Dial.parse_action("dial_rotate_left_step"),
+            Some(RotaryDirection::CounterClockWise)
+        );
+        assert_eq!(RotaryKind::Dial.parse_action("brightness_step_up"), None);
+        assert_eq!(
+            RotaryKind::BrightnessStep.parse_action("brightness_step_down"),
+            Some(RotaryDirection::CounterClockWise)
+        );
+        assert_eq!(
+            RotaryKind::BrightnessStep.parse_action("dial_rotate_left_step"),
+            None
+        );
+    }
+}
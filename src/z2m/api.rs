@@ -44,17 +44,50 @@ pub enum Message {
     BridgeConverters(Value),
 }
 
-#[derive(Serialize, Deserialize, Clone, Hash, Debug, Copy)]
+#[derive(Serialize, Deserialize, Clone, Hash, Debug, Copy, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum Availability {
     Online,
     Offline,
 }
 
+/// Payload of `<friendly_name>/availability` messages
+///
+/// Legacy z2m versions send a plain string ("online"), while newer versions
+/// send an object (`{"state": "online"}`).
+#[derive(Serialize, Deserialize, Clone, Debug, Copy)]
+#[serde(untagged)]
+pub enum AvailabilityPayload {
+    Legacy(Availability),
+    Json { state: Availability },
+}
+
+impl AvailabilityPayload {
+    #[must_use]
+    pub const fn state(self) -> Availability {
+        match self {
+            Self::Legacy(state) | Self::Json { state } => state,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Hash)]
 #[serde(transparent)]
 pub struct IeeeAddress(#[serde(deserialize_with = "ieee_address")] u64);
 
+impl IeeeAddress {
+    /// Format as colon-separated hex bytes, as used in hue `mac_address` fields
+    #[must_use]
+    pub fn to_mac_address(&self) -> String {
+        self.0
+            .to_be_bytes()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<Vec<_>>()
+            .join(":")
+    }
+}
+
 impl Debug for IeeeAddress {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "IeeeAddress({:016x})", self.0)
@@ -66,7 +99,7 @@ where
     D: Deserializer<'de>,
 {
     use serde::de::Error;
-    let s: &str = Deserialize::deserialize(deserializer)?;
+    let s: String = Deserialize::deserialize(deserializer)?;
     let num = u64::from_str_radix(s.trim_start_matches("0x"), 16).map_err(Error::custom)?;
     Ok(num)
 }
@@ -82,13 +115,48 @@ pub struct BridgeState {
     pub state: BridgeOnlineState,
 }
 
+/// Device events, published by z2m on `bridge/event`
+///
+/// See: `<zigbee2mqtt>/lib/extension/bridge.ts`
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct BridgeEvent {
-    /* FIXME: needs proper mapping */
-    /* See: <zigbee2mqtt>/lib/extension/bridge.ts */
-    pub data: Value,
-    #[serde(rename = "type")]
-    pub event_type: String,
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum BridgeEvent {
+    DeviceJoined(BridgeDeviceEvent),
+    DeviceInterview(BridgeInterviewEvent),
+    DeviceAnnounce(BridgeDeviceEvent),
+    DeviceLeave(BridgeDeviceEvent),
+    DeviceRenamed(BridgeRenameEvent),
+    #[serde(untagged)]
+    Unknown(Value),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BridgeDeviceEvent {
+    pub friendly_name: String,
+    pub ieee_address: IeeeAddress,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum InterviewStatus {
+    Started,
+    Successful,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BridgeInterviewEvent {
+    pub friendly_name: String,
+    pub ieee_address: IeeeAddress,
+    pub status: InterviewStatus,
+    #[serde(default)]
+    pub supported: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BridgeRenameEvent {
+    pub from: String,
+    pub to: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -98,6 +166,62 @@ pub struct BridgeLogging {
     pub topic: Option<String>,
 }
 
+impl BridgeLogging {
+    /// Parse a failed device command, as logged by z2m:
+    ///
+    /// `Publish 'set' 'brightness' to 'friendly/name' failed: 'Error: ...'`
+    ///
+    /// z2m does not reply to device `/set` requests, so this is the only
+    /// feedback available. Returns the device topic, and the error message.
+    #[must_use]
+    pub fn publish_failure(&self) -> Option<(&str, &str)> {
+        if self.level != "error" {
+            return None;
+        }
+
+        let msg = self.message.strip_prefix("Publish '")?;
+        let (_, rest) = msg.split_once("' to '")?;
+        let (topic, err) = rest.split_once("' failed: ")?;
+        Some((topic, err.trim_matches('\'')))
+    }
+}
+
+/// Reply to a `bridge/request/*` message, received on `bridge/response/*`
+///
+/// These are not part of [`Message`], since the topic names are not known in
+/// advance (they mirror the request topic).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BridgeResponse {
+    pub status: BridgeResponseStatus,
+    #[serde(default)]
+    pub data: Value,
+    pub error: Option<String>,
+    pub transaction: Option<Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BridgeResponseStatus {
+    Ok,
+    Error,
+}
+
+impl BridgeResponse {
+    #[must_use]
+    pub fn transaction(&self) -> Option<&str> {
+        self.transaction.as_ref().and_then(Value::as_str)
+    }
+
+    /// The error reported by z2m, if the request failed
+    #[must_use]
+    pub fn failure(&self) -> Option<&str> {
+        match self.status {
+            BridgeResponseStatus::Ok => None,
+            BridgeResponseStatus::Error => Some(self.error.as_deref().unwrap_or("unknown error")),
+        }
+    }
+}
+
 type BridgeGroups = Vec<Group>;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -365,6 +489,43 @@ impl Device {
         })
     }
 
+    #[must_use]
+    pub fn expose_property(&self, property: &str) -> Option<&Expose> {
+        self.exposes()
+            .iter()
+            .find(|exp| exp.base().property.as_deref() == Some(property))
+    }
+
+    #[must_use]
+    pub fn expose_occupancy(&self) -> bool {
+        matches!(self.expose_property("occupancy"), Some(Expose::Binary(_)))
+    }
+
+    #[must_use]
+    pub fn expose_illuminance(&self) -> bool {
+        ["illuminance", "illuminance_lux"]
+            .iter()
+            .any(|prop| matches!(self.expose_property(prop), Some(Expose::Numeric(_))))
+    }
+
+    #[must_use]
+    pub fn expose_temperature(&self) -> bool {
+        matches!(
+            self.expose_property("temperature"),
+            Some(Expose::Numeric(_))
+        )
+    }
+
+    #[must_use]
+    pub const fn is_battery_powered(&self) -> bool {
+        matches!(self.power_source, PowerSource::Battery)
+    }
+
+    #[must_use]
+    pub fn expose_sensor(&self) -> bool {
+        self.expose_occupancy() || self.expose_illuminance() || self.expose_temperature()
+    }
+
     #[must_use]
     pub fn expose_action(&self) -> bool {
         self.exposes().iter().any(|exp| {
@@ -375,6 +536,22 @@ impl Device {
             }
         })
     }
+
+    #[must_use]
+    pub fn expose_action_values(&self) -> Vec<&str> {
+        self.exposes()
+            .iter()
+            .filter_map(|exp| {
+                if let Expose::Enum(ExposeEnum { base, values }) = exp {
+                    (base.name.as_deref() == Some("action")).then_some(values)
+                } else {
+                    None
+                }
+            })
+            .flatten()
+            .filter_map(Value::as_str)
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -583,3 +760,84 @@ pub struct DeviceEndpointClusters {
     pub input: Vec<String>,
     pub output: Vec<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{BridgeEvent, BridgeLogging, BridgeResponse, InterviewStatus};
+
+    fn logging(level: &str, message: &str) -> BridgeLogging {
+        BridgeLogging {
+            level: level.to_string(),
+            message: message.to_string(),
+            topic: None,
+        }
+    }
+
+    #[test]
+    fn publish_failure() {
+        let log = logging(
+            "error",
+            "Publish 'set' 'brightness' to 'kitchen/lamp' failed: 'Error: Timeout'",
+        );
+        assert_eq!(
+            log.publish_failure(),
+            Some(("kitchen/lamp", "Error: Timeout"))
+        );
+    }
+
+    #[test]
+    fn publish_failure_other_messages() {
+        let log = logging("error", "No converter available for 'foo' (\"bar\")");
+        assert_eq!(log.publish_failure(), None);
+
+        let log = logging("info", "Publish 'set' 'state' to 'lamp' failed: 'x'");
+        assert_eq!(log.publish_failure(), None);
+    }
+
+    #[test]
+    fn bridge_response() {
+        let ok: BridgeResponse = serde_json::from_value(json!({
+            "data": {"id": "lamp"}, "status": "ok", "transaction": "bifrost-1"
+        }))
+        .unwrap();
+        assert_eq!(ok.transaction(), Some("bifrost-1"));
+        assert_eq!(ok.failure(), None);
+
+        let err: BridgeResponse = serde_json::from_value(json!({
+            "data": {}, "status": "error", "error": "Device 'foo' does not exist"
+        }))
+        .unwrap();
+        assert_eq!(err.transaction(), None);
+        assert_eq!(err.failure(), Some("Device 'foo' does not exist"));
+    }
+
+    #[test]
+    fn bridge_event() {
+        let evt: BridgeEvent = serde_json::from_value(json!({
+            "type": "device_interview",
+            "data": {
+                "friendly_name": "0x0017880100aabbcc",
+                "ieee_address": "0x0017880100aabbcc",
+                "status": "successful",
+                "supported": true,
+                "definition": {"model": "LCT015"}
+            }
+        }))
+        .unwrap();
+
+        let BridgeEvent::DeviceInterview(intv) = evt else {
+            panic!("expected interview event, got {evt:?}");
+        };
+        assert_eq!(intv.status, InterviewStatus::Successful);
+        assert_eq!(intv.supported, Some(true));
+
+        let evt: BridgeEvent = serde_json::from_value(json!({
+            "type": "some_future_event",
+            "data": {"foo": "bar"}
+        }))
+        .unwrap();
+        assert!(matches!(evt, BridgeEvent::Unknown(_)));
+    }
+}
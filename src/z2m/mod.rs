@@ -1,3 +1,4 @@
+pub mod action;
 pub mod api;
 pub mod convert;
 pub mod request;
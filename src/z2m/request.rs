@@ -12,6 +12,16 @@ pub enum Z2mRequest<'a> {
         id: u32,
     },
 
+    /// Store a scene on a single device, with an explicit state
+    SceneAdd {
+        #[serde(rename = "ID")]
+        id: u32,
+        group_id: u32,
+        name: &'a str,
+        #[serde(flatten)]
+        state: &'a DeviceUpdate,
+    },
+
     SceneRecall(u32),
 
     SceneRemove(u32),
@@ -25,3 +35,40 @@ pub enum Z2mRequest<'a> {
         value: &'a Value,
     },
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::z2m::request::Z2mRequest;
+    use crate::z2m::update::DeviceUpdate;
+
+    #[test]
+    fn scene_add_format() {
+        let state = DeviceUpdate::default()
+            .with_state(Some(true))
+            .with_brightness(Some(127.0))
+            .with_transition(Some(0.5));
+
+        let req = Z2mRequest::SceneAdd {
+            id: 3,
+            group_id: 7,
+            name: "Relax",
+            state: &state,
+        };
+
+        assert_eq!(
+            serde_json::to_value(req).unwrap(),
+            json!({
+                "scene_add": {
+                    "ID": 3,
+                    "group_id": 7,
+                    "name": "Relax",
+                    "state": "ON",
+                    "brightness": 127.0,
+                    "transition": 0.5,
+                }
+            })
+        );
+    }
+}
@@ -42,7 +42,25 @@ pub struct DeviceUpdate {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub update_available: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub battery: Option<Value>,
+    pub battery: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub battery_low: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action_step_size: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action_time: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action_transition_time: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub occupancy: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub illuminance: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub illuminance_lux: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub transition: Option<f64>,
 
@@ -58,6 +76,39 @@ impl DeviceUpdate {
         Self::default()
     }
 
+    /// Illuminance in lux.
+    ///
+    /// Older versions of z2m report both `illuminance` (raw value) and
+    /// `illuminance_lux`, while z2m 2.x only reports `illuminance` (in lux).
+    #[must_use]
+    pub fn lux(&self) -> Option<f64> {
+        self.illuminance_lux.or(self.illuminance)
+    }
+
+    /// Battery level in percent, clamped to 0-100
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn battery_level(&self) -> Option<u8> {
+        self.battery.map(|pct| pct.round().clamp(0.0, 100.0) as u8)
+    }
+
+    /// Number of steps in a rotary action (at least 1)
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn action_steps(&self) -> u32 {
+        self.action_step_size
+            .map_or(1, |steps| steps.round().max(1.0) as u32)
+    }
+
+    /// Duration of a rotary action in milliseconds (z2m reports seconds)
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn action_duration_ms(&self) -> u32 {
+        self.action_time
+            .or(self.action_transition_time)
+            .map_or(0, |secs| (secs * 1000.0).round().max(0.0) as u32)
+    }
+
     #[must_use]
     pub fn with_state(self, state: Option<bool>) -> Self {
         Self {
@@ -96,6 +147,12 @@ impl DeviceUpdate {
         }
     }
 
+    /// Transition time in seconds
+    #[must_use]
+    pub fn with_transition(self, transition: Option<f64>) -> Self {
+        Self { transition, ..self }
+    }
+
     #[must_use]
     pub fn with_gradient(self, grad: Option<LightGradientUpdate>) -> Self {
         Self {